@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+
+use bevy::{app::{Plugin, Startup, Update}, asset::{Asset, AssetEvent, AssetServer, Assets, Handle}, ecs::{component::Component, entity::Entity, event::EventReader,
+    system::{Commands, Res, ResMut, Resource}}, reflect::TypePath, render::color::Color};
+use serde::Deserialize;
+use svarog_engine::{errors::{Location, Reported, StrictMode, SvarogError}, loading::Grids, tables::{LoadedCsv, TableAssetPlugin}, text::Styled};
+
+use crate::{gameplay::{react::React, turns::Actor, Amount}, health::{Health, HealthAction, HitDieStatus}};
+
+/// A row of a `.creatures.csv` table
+#[derive(Asset, TypePath, Deserialize, Debug, Clone, PartialEq)]
+pub struct CreatureDefinition {
+    pub name: String,
+    pub glyph: String,
+    /// Draws the glyph from this tileset instead of the one of the grid it's placed on
+    pub tileset: Option<String>,
+    /// Sizes of the Hit Dice from left to right, separated by commas
+    pub hit_dice: String,
+    /// Statuses of the Hit Die in the same position, separated by commas; several on one Hit Die are joined by `+`
+    #[serde(default)]
+    pub statuses: String,
+    pub speed: Amount,
+}
+
+/// Reads a status the way it's written in tables, e.g. `guarded` or `cracked(3)`
+pub fn parse_status(value: &str) -> Option<HitDieStatus> {
+    let value = value.trim().to_lowercase();
+    let (name, amount) = match value.split_once('(') {
+        Some((name, rest)) => (name.trim(), Some(rest.strip_suffix(')')?.trim().parse::<Amount>().ok()?)),
+        None => (value.as_str(), None),
+    };
+
+    match (name, amount) {
+        ("void", None) => Some(HitDieStatus::Void),
+        ("empty", None) => Some(HitDieStatus::Empty),
+        ("grafted", None) => Some(HitDieStatus::Grafted),
+        ("temporary", None) => Some(HitDieStatus::Temporary),
+        ("guarded", None) => Some(HitDieStatus::Guarded),
+        ("fortified", Some(n)) => Some(HitDieStatus::Fortified(n)),
+        ("stifled", Some(n)) => Some(HitDieStatus::Stifled(n)),
+        ("cracked", Some(n)) => Some(HitDieStatus::Cracked(n)),
+        ("mending", Some(n)) => Some(HitDieStatus::Mending(n)),
+        _ => None,
+    }
+}
+
+fn parse_hit_die(value: &str) -> Option<Amount> {
+    value.trim().parse::<Amount>().ok().filter(|size| *size > 0)
+}
+
+impl CreatureDefinition {
+    /// Builds the `Health` of the creature by creating its Hit Dice one by one and adding their statuses.
+    /// Hit Dice and statuses that can't be read are left out; `problems` tells which ones.
+    pub fn health(&self) -> Health {
+        let mut health = Health::default();
+        let statuses = self.statuses.split(',').collect::<Vec<_>>();
+
+        for (index, size) in self.hit_dice.split(',').map(str::trim).enumerate() {
+            let Some(size) = parse_hit_die(size) else { continue; };
+            let _ = health.execute(HealthAction::Create(size));
+
+            let Some(die_statuses) = statuses.get(index) else { continue; };
+            for status in die_statuses.split('+').filter_map(parse_status) {
+                let _ = health.execute(HealthAction::AddStatus(status));
+            }
+        }
+
+        health
+    }
+
+    /// Hit Dice and statuses of this row that `health` can't read, pointing at the row they come from
+    pub fn problems(&self, at: &Location) -> Vec<SvarogError> {
+        let mut problems = vec![];
+        let malformed = |message: String| SvarogError::MalformedRow { at: at.clone(), message };
+
+        for size in self.hit_dice.split(',').map(str::trim).filter(|size| parse_hit_die(size).is_none()) {
+            problems.push(malformed(format!("creature {} has a bad Hit Die: {}", self.name, size)));
+        }
+
+        for status in self.statuses.split(&[',', '+']).map(str::trim).filter(|s| !s.is_empty() && parse_status(s).is_none()) {
+            problems.push(malformed(format!("creature {} has an unknown status: {}", self.name, status)));
+        }
+
+        problems
+    }
+}
+
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct Creature {
+    pub name: String,
+}
+
+/// Creature definitions by name, kept in sync with `bestiary.creatures.csv` as it loads and changes
+#[derive(Resource, Default, Debug)]
+pub struct Creatures {
+    pub table: Handle<LoadedCsv<CreatureDefinition>>,
+    pub definitions: HashMap<String, CreatureDefinition>,
+}
+
+impl Creatures {
+    pub fn get(&self, name: &str) -> Option<&CreatureDefinition> {
+        self.definitions.get(name)
+    }
+}
+
+/// Spawns a creature with its `Health` and `Actor`, and puts its glyph on the grid
+pub fn spawn_creature(commands: &mut Commands, grids: &mut Grids, definition: &CreatureDefinition, grid: &str, x: i32, y: i32) -> Entity {
+    grids.set_styled(grid, x, y, &Styled {
+        glyph: definition.glyph.clone(),
+        tileset: definition.tileset.clone(),
+        foreground: Color::WHITE,
+        background: None,
+        blink: false,
+    });
+
+    commands.spawn((
+        Creature { name: definition.name.clone() },
+        definition.health(),
+        Actor::new(definition.speed),
+    )).id()
+}
+
+pub fn load_creatures(asset_server: Res<AssetServer>, mut creatures: ResMut<Creatures>) {
+    creatures.table = asset_server.load("bestiary.creatures.csv");
+}
+
+pub fn index_creatures(
+    mut events: EventReader<AssetEvent<LoadedCsv<CreatureDefinition>>>,
+    tables: Res<Assets<LoadedCsv<CreatureDefinition>>>,
+    rows: Res<Assets<CreatureDefinition>>,
+    mut creatures: ResMut<Creatures>,
+    strict: Option<Res<StrictMode>>,
+    mut reported: ResMut<Reported>,
+) {
+    for event in events.read() {
+        if !event.is_loaded_with_dependencies(&creatures.table) && !event.is_modified(&creatures.table) {
+            continue;
+        }
+
+        let Some(table) = tables.get(&creatures.table) else { continue; };
+        let mut problems = vec![];
+        creatures.definitions = table.rows.iter().zip(&table.lines)
+            .filter_map(|(row, line)| rows.get(row).map(|definition| (definition, line)))
+            .map(|(definition, line)| {
+                problems.extend(definition.problems(&Location::new("bestiary.creatures.csv", *line)));
+                (definition.name.clone(), definition.clone())
+            })
+            .collect();
+
+        let count = problems.len();
+        reported.extend(problems);
+        if count > 0 && strict.as_ref().is_some_and(|strict| strict.0) {
+            panic!("Strict mode: {} problems were found in bestiary.creatures.csv", count);
+        }
+    }
+}
+
+/// Loads `bestiary.creatures.csv` into `Creatures`, reloading it when the file changes. Tables need the
+/// `.creatures.csv` extension, as Bevy picks asset loaders by what comes after the first dot of a file name.
+pub struct SvarogCreaturePlugin;
+
+impl Plugin for SvarogCreaturePlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_plugins(TableAssetPlugin::<CreatureDefinition>::new(&["creatures.csv"]))
+            .init_resource::<Creatures>()
+            .init_resource::<Reported>()
+            .add_systems(Startup, load_creatures)
+            .add_systems(Update, index_creatures);
+    }
+}
+
+#[cfg(test)]
+mod creatures_testing {
+    use svarog_engine::errors::Location;
+
+    use crate::health::HitDieStatus;
+
+    use super::{parse_status, CreatureDefinition};
+
+    #[test]
+    fn test_parse_status() {
+        assert_eq!(parse_status("guarded"), Some(HitDieStatus::Guarded));
+        assert_eq!(parse_status(" Cracked( 3 ) "), Some(HitDieStatus::Cracked(3)));
+        assert_eq!(parse_status("fortified"), None);
+        assert_eq!(parse_status("temporary(2)"), None);
+        assert_eq!(parse_status("sleepy"), None);
+    }
+
+    #[test]
+    fn test_health_from_definition() {
+
+        // [....][....] 4+4
+        //        |
+        //        Temporary, Mending(3)
+
+        let wraith = CreatureDefinition {
+            name: "wraith".to_string(),
+            glyph: "W".to_string(),
+            tileset: Some("sourcecodepro".to_string()),
+            hit_dice: "4, 4".to_string(),
+            statuses: ", temporary+mending(3)".to_string(),
+            speed: 110,
+        };
+
+        let health = wraith.health();
+        assert_eq!(health.hit_dice.len(), 2);
+        assert!(health.hit_dice[0].statuses.is_empty());
+        assert!(health.hit_dice[1].statuses.contains(&HitDieStatus::Temporary));
+        assert!(health.hit_dice[1].statuses.contains(&HitDieStatus::Mending(3)));
+        assert_eq!(*health.hit_dice[1].value, 4);
+    }
+
+    #[test]
+    fn test_unreadable_hit_dice_and_statuses_are_problems() {
+        let ghoul = CreatureDefinition {
+            name: "ghoul".to_string(),
+            glyph: "g".to_string(),
+            tileset: None,
+            hit_dice: "4, x, 0".to_string(),
+            statuses: "guarded+sleepy".to_string(),
+            speed: 100,
+        };
+
+        let health = ghoul.health();
+        assert_eq!(health.hit_dice.len(), 1);
+        assert!(health.hit_dice[0].statuses.contains(&HitDieStatus::Guarded));
+
+        let problems = ghoul.problems(&Location::new("bestiary.creatures.csv", 7))
+            .into_iter().map(|problem| problem.to_string()).collect::<Vec<_>>();
+        assert_eq!(problems.len(), 3);
+        assert!(problems.iter().all(|problem| problem.contains("bestiary.creatures.csv:7")));
+        assert!(problems.iter().any(|problem| problem.contains("sleepy")));
+    }
+
+    #[test]
+    fn test_readable_definition_has_no_problems() {
+        let rat = CreatureDefinition {
+            name: "rat".to_string(),
+            glyph: "r".to_string(),
+            tileset: None,
+            hit_dice: "2".to_string(),
+            statuses: "cracked(2)".to_string(),
+            speed: 120,
+        };
+
+        assert!(rat.problems(&Location::new("bestiary.creatures.csv", 2)).is_empty());
+    }
+}
@@ -2,6 +2,7 @@
 pub mod value;
 pub mod react;
 pub mod random;
+pub mod turns;
 
 pub type Time = i32;
 pub type Amount = i32;
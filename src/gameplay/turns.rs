@@ -0,0 +1,239 @@
+use std::cmp::Reverse;
+
+use bevy::{app::{Plugin, Update}, ecs::{component::Component, entity::Entity, event::{Event, EventReader, EventWriter}, query::{Added, With},
+    schedule::IntoSystemConfigs, system::{Query, Res, ResMut, Resource}}};
+use priority_queue::PriorityQueue;
+use serde::{Deserialize, Serialize};
+
+use super::{Amount, Time};
+
+/// Energy an actor needs to take a turn, and what an ordinary action costs
+pub const TURN_COST: Amount = 100;
+
+/// Something that takes turns. Every unit of time it gains `speed` energy, and it can act once it has `TURN_COST`.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Actor {
+    pub speed: Amount,
+    pub energy: Amount,
+}
+
+impl Actor {
+    /// A new actor starts with enough energy to act straight away
+    pub fn new(speed: Amount) -> Self {
+        Self { speed, energy: TURN_COST }
+    }
+
+    /// How long until the actor has the energy for another turn
+    pub fn recovery(&self) -> Time {
+        let speed = self.speed.max(1);
+        let deficit = (TURN_COST - self.energy).max(0);
+        (deficit + speed - 1) / speed
+    }
+}
+
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameClock {
+    pub time: Time,
+    /// How many turns were taken by all actors together
+    pub turns: u64,
+}
+
+/// Who acts next. Turns at the same time go in the order they were scheduled, so the same
+/// game always plays out the same way.
+#[derive(Resource, Default, Debug)]
+pub struct TurnScheduler {
+    queue: PriorityQueue<Entity, Reverse<(Time, u64)>>,
+    sequence: u64,
+    /// The actor whose turn it is, if it hasn't ended it yet
+    pub current: Option<Entity>,
+}
+
+impl TurnScheduler {
+    /// Schedules a turn for the entity, replacing the one it had scheduled
+    pub fn schedule(&mut self, entity: Entity, at: Time) {
+        self.sequence += 1;
+        self.queue.push(entity, Reverse((at, self.sequence)));
+    }
+
+    pub fn unschedule(&mut self, entity: Entity) -> Option<Time> {
+        self.queue.remove(&entity).map(|(_, Reverse((time, _)))| time)
+    }
+
+    pub fn peek(&self) -> Option<(Entity, Time)> {
+        self.queue.peek().map(|(entity, Reverse((time, _)))| (*entity, *time))
+    }
+
+    pub fn pop(&mut self) -> Option<(Entity, Time)> {
+        self.queue.pop().map(|(entity, Reverse((time, _)))| (entity, time))
+    }
+
+    pub fn is_scheduled(&self, entity: Entity) -> bool {
+        self.queue.get(&entity).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+/// Sent for every unit of time the clock moves forward, for anything that counts down in time
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tick {
+    pub time: Time,
+}
+
+/// Sent when it's an actor's turn; nobody else acts until it sends `TurnEnded`
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TurnStarted {
+    pub entity: Entity,
+    pub time: Time,
+}
+
+/// Sent by whatever controls an actor once it has acted, with the energy the action took
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TurnEnded {
+    pub entity: Entity,
+    pub cost: Amount,
+}
+
+pub fn turn_schedule_new(clock: Res<GameClock>, mut scheduler: ResMut<TurnScheduler>, actors: Query<(Entity, &Actor), Added<Actor>>) {
+    for (entity, actor) in &actors {
+        scheduler.schedule(entity, clock.time + actor.recovery());
+    }
+}
+
+/// Takes what ended turns cost out of the actors' energy and schedules their next turns
+pub fn turn_end(clock: Res<GameClock>, mut scheduler: ResMut<TurnScheduler>, mut ended: EventReader<TurnEnded>, mut actors: Query<&mut Actor>) {
+    for &TurnEnded { entity, cost } in ended.read() {
+        if scheduler.current == Some(entity) {
+            scheduler.current = None;
+        }
+
+        let Ok(mut actor) = actors.get_mut(entity) else { continue; };
+        actor.energy -= cost;
+        let wait = actor.recovery();
+        actor.energy += wait * actor.speed.max(1);
+        scheduler.schedule(entity, clock.time + wait);
+    }
+}
+
+/// Starts the next turn once the current one is over, moving the clock forward to it
+pub fn turn_advance(
+    mut clock: ResMut<GameClock>,
+    mut scheduler: ResMut<TurnScheduler>,
+    actors: Query<(), With<Actor>>,
+    mut ticks: EventWriter<Tick>,
+    mut started: EventWriter<TurnStarted>,
+) {
+    if let Some(current) = scheduler.current {
+        if actors.contains(current) {
+            return;
+        }
+        scheduler.current = None;
+    }
+
+    while let Some((entity, time)) = scheduler.pop() {
+        // actors that were despawned since they got scheduled are dropped here
+        if !actors.contains(entity) {
+            continue;
+        }
+
+        while clock.time < time {
+            clock.time += 1;
+            ticks.send(Tick { time: clock.time });
+        }
+
+        clock.turns += 1;
+        scheduler.current = Some(entity);
+        started.send(TurnStarted { entity, time: clock.time });
+        return;
+    }
+}
+
+pub struct SvarogTurnPlugin;
+
+impl Plugin for SvarogTurnPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_resource::<GameClock>()
+            .init_resource::<TurnScheduler>()
+            .add_event::<Tick>()
+            .add_event::<TurnStarted>()
+            .add_event::<TurnEnded>()
+            .add_systems(Update, (turn_schedule_new, turn_end, turn_advance).chain());
+    }
+}
+
+#[cfg(test)]
+mod turns_testing {
+    use bevy::{app::{App, Update}, ecs::{entity::Entity, event::{EventReader, EventWriter}, schedule::IntoSystemConfigs, system::{ResMut, Resource}}};
+
+    use crate::gameplay::Time;
+
+    use super::{turn_advance, Actor, GameClock, SvarogTurnPlugin, TurnEnded, TurnScheduler, TurnStarted, TURN_COST};
+
+    #[test]
+    fn test_scheduler_orders_by_time_then_sequence() {
+        let (a, b, c) = (Entity::from_raw(1), Entity::from_raw(2), Entity::from_raw(3));
+        let mut scheduler = TurnScheduler::default();
+        scheduler.schedule(a, 5);
+        scheduler.schedule(b, 2);
+        scheduler.schedule(c, 5);
+
+        assert_eq!(scheduler.pop(), Some((b, 2)));
+        assert_eq!(scheduler.pop(), Some((a, 5)));
+        assert_eq!(scheduler.pop(), Some((c, 5)));
+        assert_eq!(scheduler.pop(), None);
+    }
+
+    #[test]
+    fn test_rescheduling_replaces_turn() {
+        let a = Entity::from_raw(1);
+        let mut scheduler = TurnScheduler::default();
+        scheduler.schedule(a, 5);
+        scheduler.schedule(a, 1);
+
+        assert_eq!(scheduler.len(), 1);
+        assert_eq!(scheduler.peek(), Some((a, 1)));
+    }
+
+    #[test]
+    fn test_recovery_depends_on_speed() {
+        assert_eq!(Actor::new(100).recovery(), 0);
+        assert_eq!(Actor { speed: 100, energy: 0 }.recovery(), 1);
+        assert_eq!(Actor { speed: 30, energy: 0 }.recovery(), 4);
+        assert_eq!(Actor { speed: 50, energy: 40 }.recovery(), 2);
+    }
+
+    #[derive(Resource, Default)]
+    struct Log(Vec<(Entity, Time)>);
+
+    fn act_immediately(mut started: EventReader<TurnStarted>, mut ended: EventWriter<TurnEnded>, mut log: ResMut<Log>) {
+        for turn in started.read() {
+            log.0.push((turn.entity, turn.time));
+            ended.send(TurnEnded { entity: turn.entity, cost: TURN_COST });
+        }
+    }
+
+    #[test]
+    fn test_faster_actors_act_more_often() {
+        let mut app = App::new();
+        app.add_plugins(SvarogTurnPlugin)
+            .init_resource::<Log>()
+            .add_systems(Update, act_immediately.after(turn_advance));
+
+        let fast = app.world.spawn(Actor::new(100)).id();
+        let slow = app.world.spawn(Actor::new(50)).id();
+
+        for _ in 0..6 {
+            app.update();
+        }
+
+        assert_eq!(app.world.resource::<Log>().0, vec![ (fast, 0), (slow, 0), (fast, 1), (slow, 2), (fast, 2), (fast, 3) ]);
+        assert_eq!(app.world.resource::<GameClock>().time, 3);
+        assert_eq!(app.world.resource::<GameClock>().turns, 6);
+    }
+}
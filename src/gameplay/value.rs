@@ -1,6 +1,8 @@
 use std::ops::{Deref, DerefMut};
 
-#[derive(Debug)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Value {
     total: i32,
     current: i32,
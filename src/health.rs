@@ -1,16 +1,17 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use bevy::ecs::component::Component;
 use crate::gameplay::{value::Value, Amount, Index, react::React, Time};
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
-#[derive(Default, Component, Debug)]
+#[derive(Default, Component, Debug, Clone, Serialize, Deserialize)]
 pub struct Health {
     pub hit_dice: Vec<HitDie>,
 }
 
-#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug, Serialize, Deserialize)]
 pub enum HitDieStatus {
     /// A `Void` Hit Die has no influence
     Void,
@@ -33,24 +34,46 @@ pub enum HitDieStatus {
     Mending(Time),
 }
 
-#[derive(Debug)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum Effort {
     Uncommited,
     Commited(Commitment)
 }
 
-#[derive(Debug)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum Commitment {
+    /// Committed for the next number of turns
     Turn(Amount),
+    /// Committed until the encounter ends
     Encounter,
+    /// Committed until the next rest
     Rest,
 }
 
-#[derive(Debug)]
+impl Commitment {
+    /// Whether releasing this kind of commitment also releases the other one:
+    /// the end of a rest releases everything, and the end of an encounter releases turns too
+    pub fn covers(&self, other: &Commitment) -> bool {
+        match (self, other) {
+            (Commitment::Rest, _) => true,
+            (Commitment::Encounter, Commitment::Encounter | Commitment::Turn(_)) => true,
+            (Commitment::Turn(_), Commitment::Turn(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HitDie {
     pub value: Value,
     pub effort: Effort,
     pub statuses: HashSet<HitDieStatus>,
+    /// Time this Hit Die has been around for
+    #[serde(default)]
+    pub elapsed: Time,
+    /// When each `Cracked` and `Mending` status was added, which their periods count from
+    #[serde(default)]
+    pub started: HashMap<HitDieStatus, Time>,
 }
 
 impl HitDie {
@@ -59,11 +82,74 @@ impl HitDie {
             value: Value::new(size),
             effort: Effort::Uncommited, 
             statuses: HashSet::new(),
+            elapsed: 0,
+            started: HashMap::new(),
+        }
+    }
+
+    /// `Void`, `Grafted` and `Stifled` Hit Dice give no effort
+    pub fn gives_effort(&self) -> bool {
+        !self.statuses.iter().any(|s| matches!(s, HitDieStatus::Void | HitDieStatus::Grafted | HitDieStatus::Stifled(_)))
+    }
+
+    /// Whether this Hit Die's effort can be committed right now
+    pub fn is_available(&self) -> bool {
+        self.effort == Effort::Uncommited && self.gives_effort()
+    }
+
+    /// Time since a status was added
+    fn since(&self, status: HitDieStatus) -> Time {
+        self.elapsed - self.started.get(&status).copied().unwrap_or(0)
+    }
+
+    /// Advances the timed statuses by a single unit of time: `Stifled` counts down,
+    /// while `Cracked` and `Mending` chip and heal whenever their period comes around
+    fn tick(&mut self) -> Vec<HitDieActionResponse> {
+        self.elapsed += 1;
+        let mut responses = vec![];
+
+        if let Effort::Commited(Commitment::Turn(turns)) = self.effort {
+            if turns > 1 {
+                self.effort = Effort::Commited(Commitment::Turn(turns - 1));
+            } else {
+                self.effort = Effort::Uncommited;
+                responses.push(HitDieActionResponse::ReleaseResponse);
+            }
+        }
+
+        let timed = |statuses: &HashSet<HitDieStatus>, f: fn(&HitDieStatus) -> Option<Time>| statuses.iter().find_map(f);
+
+        if let Some(turns) = timed(&self.statuses, |s| if let HitDieStatus::Stifled(n) = s { Some(*n) } else { None }) {
+            self.statuses.remove(&HitDieStatus::Stifled(turns));
+            if turns > 1 {
+                self.statuses.insert(HitDieStatus::Stifled(turns - 1));
+            } else {
+                responses.push(HitDieActionResponse::ExpiredResponse(HitDieStatus::Stifled(0)));
+            }
+        }
+
+        if let Some(period) = timed(&self.statuses, |s| if let HitDieStatus::Cracked(n) = s { Some(*n) } else { None }) {
+            if period > 0 && self.since(HitDieStatus::Cracked(period)) % period == 0 && *self.value > 0 {
+                let response = self.execute(HealthAction::Chip(1));
+                let shattered = response == HitDieActionResponse::ShatterResponse;
+                responses.push(response);
+                if shattered {
+                    return responses;
+                }
+            }
         }
+
+        if let Some(period) = timed(&self.statuses, |s| if let HitDieStatus::Mending(n) = s { Some(*n) } else { None }) {
+            if period > 0 && self.since(HitDieStatus::Mending(period)) % period == 0 && *self.value < self.value.total() {
+                responses.push(self.execute(HealthAction::Heal(1)));
+            }
+        }
+
+        responses
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub enum HealthAction {
     /// The `Create` action adds a Hit Die of some size to the health bar
     Create(Amount),
@@ -87,6 +173,13 @@ pub enum HealthAction {
     Shatter,
     /// The `Fortify` action makes the right-most Hit Die **Fortified**
     Fortify(Amount),
+    /// The `Tick` action lets a set amount of time pass for every Hit Die, running down
+    /// **Stifled** and applying **Cracked** and **Mending**
+    Tick(Time),
+    /// The `Commit` action commits the effort of the right-most Hit Die that can give it
+    Commit(Commitment),
+    /// The `Release` action frees the effort of every Hit Die whose commitment is covered by the given one
+    Release(Commitment),
 }
 
 #[derive(PartialEq, Eq, Clone, Debug)]
@@ -100,6 +193,9 @@ pub enum HitDieActionResponse {
     ShatterResponse,
     FortifiedResponse(Amount),
     RecoveryResponse,
+    ExpiredResponse(HitDieStatus),
+    CommitResponse,
+    ReleaseResponse,
 }
 
 #[derive(PartialEq, Eq, Clone, Debug)]
@@ -114,6 +210,9 @@ pub enum HealthActionResponse {
     BreakResponse(Index),
     MendResponse(Index),
     ShatterResponse(Index),
+    ExpiredResponse(Index, HitDieStatus),
+    CommitResponse(Index),
+    ReleaseResponse(Index),
 }
 
 impl From<(&HitDieActionResponse, Index)> for HealthActionResponse {
@@ -128,6 +227,9 @@ impl From<(&HitDieActionResponse, Index)> for HealthActionResponse {
             HitDieActionResponse::FortifiedResponse(f) => HealthActionResponse::FortifiedResponse(value.1, *f),
             HitDieActionResponse::RecoveryResponse => HealthActionResponse::RecoveryResponse(value.1),
             HitDieActionResponse::ShatterResponse => HealthActionResponse::ShatterResponse(value.1),
+            HitDieActionResponse::ExpiredResponse(status) => HealthActionResponse::ExpiredResponse(value.1, *status),
+            HitDieActionResponse::CommitResponse => HealthActionResponse::CommitResponse(value.1),
+            HitDieActionResponse::ReleaseResponse => HealthActionResponse::ReleaseResponse(value.1),
         }
     }
 }
@@ -139,11 +241,14 @@ impl React<HealthAction, HitDieActionResponse> for HitDie {
                 HitDieActionResponse::ChipResponse(self.value.reduce(n as u32))
             },
             HealthAction::AddStatus(status) => {
-                self.statuses.insert(status);
+                if self.statuses.insert(status) && matches!(status, HitDieStatus::Cracked(_) | HitDieStatus::Mending(_)) {
+                    self.started.insert(status, self.elapsed);
+                }
                 HitDieActionResponse::None
             },
             HealthAction::RemoveStatus(status) => {
                 self.statuses.remove(&status);
+                self.started.remove(&status);
                 HitDieActionResponse::None
             },
             HealthAction::Heal(n) if n > 0 => {
@@ -187,6 +292,17 @@ impl React<HealthAction, HitDieActionResponse> for HitDie {
 
                 HitDieActionResponse::FortifiedResponse(value)
             },
+            HealthAction::Commit(commitment) if self.is_available() => {
+                self.effort = Effort::Commited(commitment);
+                HitDieActionResponse::CommitResponse
+            },
+            HealthAction::Release(release) => match self.effort {
+                Effort::Commited(commitment) if release.covers(&commitment) => {
+                    self.effort = Effort::Uncommited;
+                    HitDieActionResponse::ReleaseResponse
+                },
+                _ => HitDieActionResponse::None,
+            },
             _ => HitDieActionResponse::None,
         };
 
@@ -365,14 +481,66 @@ impl React<HealthAction, Vec<HealthActionResponse>> for Health {
                     vec![ HealthActionResponse::None ]
                 }
             },
+            HealthAction::Tick(time) if time > 0 => {
+                let mut result = vec![];
+                for _ in 0..time {
+                    for index in (0..self.hit_dice.len()).rev() {
+                        let responses = self.hit_dice[index].tick();
+                        result.extend(responses.iter().map(|response| HealthActionResponse::from((response, index))));
+
+                        if responses.contains(&HitDieActionResponse::ShatterResponse) {
+                            self.hit_dice.remove(index);
+                        }
+                    }
+                }
+
+                result
+            },
+            HealthAction::Commit(commitment) => {
+                match self.hit_dice.iter().rposition(|hd| hd.is_available()) {
+                    Some(index) => vec![ (&self.hit_dice[index].execute(HealthAction::Commit(commitment)), index).into() ],
+                    None => vec![ HealthActionResponse::None ],
+                }
+            },
+            HealthAction::Release(release) => {
+                let result: Vec<HealthActionResponse> = self.hit_dice.iter_mut().enumerate()
+                    .map(|(index, hd)| (hd.execute(HealthAction::Release(release)), index))
+                    .filter(|(response, _)| *response == HitDieActionResponse::ReleaseResponse)
+                    .map(|(response, index)| (&response, index).into())
+                    .collect_vec();
+
+                if result.is_empty() { vec![ HealthActionResponse::None ] } else { result }
+            },
             _ => vec![],
         }
     }
 }
 
+impl Health {
+    /// Dead is when there are no hit points left in any Hit Die, or no Hit Dice left at all
+    pub fn is_dead(&self) -> bool {
+        self.hit_dice.iter().all(|hd| *hd.value == 0)
+    }
+
+    /// How many Hit Dice could have their effort committed right now
+    pub fn available_effort(&self) -> usize {
+        self.hit_dice.iter().filter(|hd| hd.is_available()).count()
+    }
+
+    /// Releases the effort committed for turns and for the encounter
+    pub fn end_encounter(&mut self) -> Vec<HealthActionResponse> {
+        self.execute(HealthAction::Release(Commitment::Encounter))
+    }
+
+    /// Releases all committed effort
+    pub fn end_rest(&mut self) -> Vec<HealthActionResponse> {
+        self.execute(HealthAction::Release(Commitment::Rest))
+    }
+}
+
 #[cfg(test)]
 mod health_testing {
-    use crate::{gameplay::react::React, health::{HealthActionResponse, HitDieStatus}};
+    use crate::{gameplay::react::React, health::{Commitment, HealthActionResponse, HitDieStatus}};
 
     use super::Health;
 
@@ -856,4 +1024,201 @@ mod health_testing {
         assert_eq!(result, vec![ HealthActionResponse::None ]);
         assert!(health.hit_dice.last().is_none());
     }
-}
\ No newline at end of file
+
+    #[test]
+    pub fn test_cracked_hit_dice_chip_over_time() {
+
+        // [......] 6
+        //  |
+        //  Cracked(2)
+
+        let mut health = Health::default();
+        let _ = health.execute(super::HealthAction::Create(6));
+        let _ = health.execute(super::HealthAction::AddStatus(HitDieStatus::Cracked(2)));
+
+        // [......] 6   -TICK 4->   [....xx] 4 / chipped on turns 2 and 4
+        //  |                        |
+        //  Cracked(2)               Cracked(2)
+
+        let result = health.execute(super::HealthAction::Tick(4));
+        assert_eq!(result, vec![ HealthActionResponse::ChipResponse(0, 0), HealthActionResponse::ChipResponse(0, 0) ]);
+        assert_eq!(*health.hit_dice.last().unwrap().value, 4);
+    }
+
+    #[test]
+    pub fn test_cracked_period_counts_from_when_it_was_added() {
+
+        // [......] 6   -TICK 3->   [......] 6   +Cracked(2)
+
+        let mut health = Health::default();
+        let _ = health.execute(super::HealthAction::Create(6));
+        let _ = health.execute(super::HealthAction::Tick(3));
+        let _ = health.execute(super::HealthAction::AddStatus(HitDieStatus::Cracked(2)));
+
+        // [......] 6   -TICK 1->   [......] 6 / only one turn cracked
+        //              -TICK 1->   [.....x] 5 / chipped two turns after cracking
+
+        assert!(health.execute(super::HealthAction::Tick(1)).is_empty());
+        assert_eq!(*health.hit_dice.last().unwrap().value, 6);
+        assert_eq!(health.execute(super::HealthAction::Tick(1)), vec![ HealthActionResponse::ChipResponse(0, 0) ]);
+        assert_eq!(*health.hit_dice.last().unwrap().value, 5);
+    }
+
+    #[test]
+    pub fn test_mending_hit_dice_heal_over_time() {
+
+        // [...xxx] 3
+        //  |
+        //  Mending(1)
+
+        let mut health = Health::default();
+        let _ = health.execute(super::HealthAction::Create(6));
+        let _ = health.execute(super::HealthAction::Chip(3));
+        let _ = health.execute(super::HealthAction::AddStatus(HitDieStatus::Mending(1)));
+
+        // [...xxx] 3   -TICK 5->   [......] 6 / healed on turns 1 to 3, then already full
+
+        let result = health.execute(super::HealthAction::Tick(5));
+        assert_eq!(result, vec![ HealthActionResponse::HealResponse(0, 0); 3 ]);
+        assert_eq!(*health.hit_dice.last().unwrap().value, 6);
+    }
+
+    #[test]
+    pub fn test_stifled_hit_dice_expire() {
+
+        // [......] 6
+        //  |
+        //  Stifled(2)
+
+        let mut health = Health::default();
+        let _ = health.execute(super::HealthAction::Create(6));
+        let _ = health.execute(super::HealthAction::AddStatus(HitDieStatus::Stifled(2)));
+
+        // [......] 6   -TICK 1->   [......] 6
+        //  |                        |
+        //  Stifled(2)               Stifled(1)
+
+        let result = health.execute(super::HealthAction::Tick(1));
+        assert_eq!(result, vec![]);
+        assert!(health.hit_dice.last().unwrap().statuses.contains(&HitDieStatus::Stifled(1)));
+
+        // [......] 6   -TICK 1->   [......] 6
+        //  |
+        //  Stifled(1)
+
+        let result = health.execute(super::HealthAction::Tick(1));
+        assert_eq!(result, vec![ HealthActionResponse::ExpiredResponse(0, HitDieStatus::Stifled(0)) ]);
+        assert!(health.hit_dice.last().unwrap().statuses.is_empty());
+    }
+
+    #[test]
+    pub fn test_cracked_temporary_hit_dice_shatter() {
+
+        // [......][.] 6+1
+        //          |
+        //          Temporary, Cracked(1)
+
+        let mut health = Health::default();
+        let _ = health.execute(super::HealthAction::Create(6));
+        let _ = health.execute(super::HealthAction::Create(1));
+        let _ = health.execute(super::HealthAction::AddStatus(HitDieStatus::Temporary));
+        let _ = health.execute(super::HealthAction::AddStatus(HitDieStatus::Cracked(1)));
+
+        // [......][.] 6+1   -TICK 1->   [......] 6 / the cracked die crumbled away
+
+        let result = health.execute(super::HealthAction::Tick(1));
+        assert_eq!(result, vec![ HealthActionResponse::ShatterResponse(1) ]);
+        assert_eq!(health.hit_dice.len(), 1);
+    }
+
+    #[test]
+    pub fn test_commit_right_most_available_hit_die() {
+
+        // [......][......] 6+6
+        //          |
+        //          Grafted
+
+        let mut health = Health::default();
+        let _ = health.execute(super::HealthAction::Create(6));
+        let _ = health.execute(super::HealthAction::Create(6));
+        let _ = health.execute(super::HealthAction::AddStatus(HitDieStatus::Grafted));
+        assert_eq!(health.available_effort(), 1);
+
+        // [......][......] 6+6   -COMMIT->   [......][......] 6+6
+        //          |                          |       |
+        //          Grafted                    Enc.    Grafted
+
+        let result = health.execute(super::HealthAction::Commit(Commitment::Encounter));
+        assert_eq!(result, vec![ HealthActionResponse::CommitResponse(0) ]);
+        assert_eq!(health.available_effort(), 0);
+
+        let result = health.execute(super::HealthAction::Commit(Commitment::Encounter));
+        assert_eq!(result, vec![ HealthActionResponse::None ]);
+    }
+
+    #[test]
+    pub fn test_available_effort_skips_void_and_stifled() {
+
+        // [......][......][......] 6+6+6
+        //          |       |
+        //          Stifled Void
+
+        let mut health = Health::default();
+        let _ = health.execute(super::HealthAction::Create(6));
+        let _ = health.execute(super::HealthAction::Create(6));
+        let _ = health.execute(super::HealthAction::AddStatus(HitDieStatus::Stifled(1)));
+        let _ = health.execute(super::HealthAction::Create(6));
+        let _ = health.execute(super::HealthAction::Break);
+        assert_eq!(health.available_effort(), 1);
+
+        // -TICK 1->   Stifled wears off
+
+        let _ = health.execute(super::HealthAction::Tick(1));
+        assert_eq!(health.available_effort(), 2);
+    }
+
+    #[test]
+    pub fn test_turn_commitment_released_by_ticks() {
+
+        // [......] 6
+        //  |
+        //  Turn(2)
+
+        let mut health = Health::default();
+        let _ = health.execute(super::HealthAction::Create(6));
+        let _ = health.execute(super::HealthAction::Commit(Commitment::Turn(2)));
+
+        let result = health.execute(super::HealthAction::Tick(1));
+        assert_eq!(result, vec![]);
+        assert_eq!(health.available_effort(), 0);
+
+        let result = health.execute(super::HealthAction::Tick(1));
+        assert_eq!(result, vec![ HealthActionResponse::ReleaseResponse(0) ]);
+        assert_eq!(health.available_effort(), 1);
+    }
+
+    #[test]
+    pub fn test_encounter_and_rest_release_commitments() {
+
+        // [......][......][......] 6+6+6
+        //  |       |       |
+        //  Turn(5) Enc.    Rest
+
+        let mut health = Health::default();
+        let _ = health.execute(super::HealthAction::Create(6));
+        let _ = health.execute(super::HealthAction::Create(6));
+        let _ = health.execute(super::HealthAction::Create(6));
+        let _ = health.execute(super::HealthAction::Commit(Commitment::Rest));
+        let _ = health.execute(super::HealthAction::Commit(Commitment::Encounter));
+        let _ = health.execute(super::HealthAction::Commit(Commitment::Turn(5)));
+        assert_eq!(health.available_effort(), 0);
+
+        let result = health.end_encounter();
+        assert_eq!(result, vec![ HealthActionResponse::ReleaseResponse(0), HealthActionResponse::ReleaseResponse(1) ]);
+        assert_eq!(health.available_effort(), 2);
+
+        let result = health.end_rest();
+        assert_eq!(result, vec![ HealthActionResponse::ReleaseResponse(2) ]);
+        assert_eq!(health.available_effort(), 3);
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+
+use bevy::{app::{Plugin, Update}, ecs::{component::Component, entity::Entity, query::{Changed, Or}, removal_detection::RemovedComponents, system::{Local, Query, Res, ResMut, Resource}},
+    render::color::Color};
+use svarog_engine::loading::Grids;
+
+use crate::health::{Health, HitDie, HitDieStatus};
+
+/// Draws the `Health` of the same entity into a row of a grid, one segment per Hit Die
+#[derive(Component, Debug, Clone)]
+pub struct HealthBar {
+    pub grid: String,
+    pub x: i32,
+    pub y: i32,
+    /// Cells the bar may take up; they're cleared before every redraw
+    pub width: i32,
+}
+
+/// Glyph names and colors health bars are drawn with; the glyphs have to exist in the grid's tileset
+#[derive(Resource, Debug, Clone)]
+pub struct HealthBarStyle {
+    pub filled: String,
+    pub lost: String,
+    pub void: String,
+    pub separator: String,
+    pub health: Color,
+    pub lost_color: Color,
+    pub void_color: Color,
+    pub empty_color: Color,
+    pub temporary_color: Color,
+    pub guarded_color: Color,
+    pub fortified_color: Color,
+}
+
+impl Default for HealthBarStyle {
+    fn default() -> Self {
+        Self {
+            filled: "block".to_string(),
+            lost: ".".to_string(),
+            void: "x".to_string(),
+            separator: "left".to_string(),
+            health: Color::rgb_u8(200, 40, 40),
+            lost_color: Color::rgb_u8(90, 90, 90),
+            void_color: Color::rgb_u8(60, 60, 60),
+            empty_color: Color::rgb_u8(120, 30, 30),
+            temporary_color: Color::rgb_u8(80, 180, 220),
+            guarded_color: Color::rgb_u8(220, 180, 60),
+            fortified_color: Color::rgb_u8(90, 120, 230),
+        }
+    }
+}
+
+fn fortified(hit_die: &HitDie) -> Option<i32> {
+    hit_die.statuses.iter().find_map(|s| if let HitDieStatus::Fortified(n) = s { Some(*n) } else { None })
+}
+
+/// Glyph and color of every cell of a health bar, left to right. Each Hit Die takes as many cells as
+/// it has hit points, and is followed by a separator, or by its armor if it's `Fortified`.
+pub fn health_bar_cells(health: &Health, style: &HealthBarStyle) -> Vec<(String, Color)> {
+    let mut cells = vec![];
+
+    for (index, hit_die) in health.hit_dice.iter().enumerate() {
+        let has = |status| hit_die.statuses.contains(&status);
+        let color = if has(HitDieStatus::Guarded) {
+            style.guarded_color
+        } else if has(HitDieStatus::Temporary) {
+            style.temporary_color
+        } else {
+            style.health
+        };
+
+        for point in 0..hit_die.value.total() {
+            cells.push(if has(HitDieStatus::Void) {
+                (style.void.clone(), style.void_color)
+            } else if has(HitDieStatus::Empty) {
+                (style.lost.clone(), style.empty_color)
+            } else if point < *hit_die.value {
+                (style.filled.clone(), color)
+            } else {
+                (style.lost.clone(), style.lost_color)
+            });
+        }
+
+        if let Some(armor) = fortified(hit_die) {
+            cells.push((armor.clamp(0, 9).to_string(), style.fortified_color));
+        } else if index + 1 < health.hit_dice.len() {
+            cells.push((style.separator.clone(), style.lost_color));
+        }
+    }
+
+    cells
+}
+
+fn clear_bar(grids: &mut Grids, bar: &HealthBar) {
+    for i in 0..bar.width {
+        if grids.contains(&bar.grid, bar.x + i, bar.y) {
+            grids.set(&bar.grid, bar.x + i, bar.y, "");
+        }
+    }
+}
+
+/// Redraws the health bars whose `Health` or placement changed, clearing the cells of those that moved,
+/// shrank or went away
+#[allow(clippy::type_complexity)]
+pub fn draw_health_bars(
+    style: Res<HealthBarStyle>,
+    mut grids: ResMut<Grids>,
+    bars: Query<(Entity, &Health, &HealthBar), Or<(Changed<Health>, Changed<HealthBar>)>>,
+    mut removed: RemovedComponents<HealthBar>,
+    mut drawn: Local<HashMap<Entity, HealthBar>>,
+) {
+    for entity in removed.read() {
+        if let Some(old) = drawn.remove(&entity) {
+            clear_bar(&mut grids, &old);
+        }
+    }
+
+    for (entity, health, bar) in &bars {
+        if let Some(old) = drawn.insert(entity, bar.clone()) {
+            clear_bar(&mut grids, &old);
+        }
+        clear_bar(&mut grids, bar);
+
+        for (i, (glyph, color)) in health_bar_cells(health, &style).iter().enumerate().take(bar.width.max(0) as usize) {
+            let x = bar.x + i as i32;
+            if grids.contains(&bar.grid, x, bar.y) {
+                grids.set_colored(&bar.grid, x, bar.y, glyph, *color, None);
+            }
+        }
+    }
+}
+
+pub struct SvarogHealthBarPlugin;
+
+impl Plugin for SvarogHealthBarPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_resource::<HealthBarStyle>()
+            .add_systems(Update, draw_health_bars);
+    }
+}
+
+#[cfg(test)]
+mod health_bar_testing {
+    use bevy::app::{App, Update};
+    use svarog_engine::loading::Grids;
+
+    use crate::{gameplay::react::React, health::{Health, HealthAction, HitDieStatus}};
+
+    use super::{draw_health_bars, health_bar_cells, HealthBar, HealthBarStyle};
+
+    #[test]
+    fn test_health_bar_segments() {
+
+        // [...xx][..] 3+2
+        //         |
+        //         Fortified(2), Guarded
+
+        let mut health = Health::default();
+        let _ = health.execute(HealthAction::Create(5));
+        let _ = health.execute(HealthAction::Chip(2));
+        let _ = health.execute(HealthAction::Create(2));
+        let _ = health.execute(HealthAction::Fortify(2));
+        let _ = health.execute(HealthAction::AddStatus(HitDieStatus::Guarded));
+
+        let style = HealthBarStyle::default();
+        let cells = health_bar_cells(&health, &style);
+        let glyphs = cells.iter().map(|(glyph, _)| glyph.as_str()).collect::<Vec<_>>();
+        assert_eq!(glyphs, vec![ "block", "block", "block", ".", ".", "left", "block", "block", "2" ]);
+        assert_eq!(cells[6].1, style.guarded_color);
+    }
+
+    #[test]
+    fn test_moved_and_shrunk_bars_leave_no_cells_behind() {
+        let mut grids = Grids::default();
+        grids.load("grids.csv", "name | width | height | depth | x | y | kind  | tileset | align\n\
+                                 hud  |    12 |      2 |     0 | 0 | 0 | glyph | tiny    | None\n");
+
+        let mut app = App::new();
+        app.insert_resource(grids).init_resource::<HealthBarStyle>().add_systems(Update, draw_health_bars);
+
+        let mut health = Health::default();
+        let _ = health.execute(HealthAction::Create(4));
+        let _ = health.execute(HealthAction::Create(4));
+        let bar = app.world.spawn((health, HealthBar { grid: "hud".to_string(), x: 0, y: 0, width: 12 })).id();
+        app.update();
+
+        let drawn = |app: &App, y: i32| (0..12).filter(|x| !app.world.resource::<Grids>().get("hud", *x, y).unwrap().is_empty()).count();
+        assert_eq!(drawn(&app, 0), 9);
+
+        app.world.get_mut::<HealthBar>(bar).unwrap().y = 1;
+        app.update();
+        assert_eq!((drawn(&app, 0), drawn(&app, 1)), (0, 9));
+
+        app.world.get_mut::<HealthBar>(bar).unwrap().width = 3;
+        app.update();
+        assert_eq!(drawn(&app, 1), 3);
+
+        app.world.entity_mut(bar).remove::<HealthBar>();
+        app.update();
+        assert_eq!(drawn(&app, 1), 0);
+    }
+}
@@ -0,0 +1,97 @@
+use bevy::{app::{Plugin, Update}, ecs::{entity::Entity, event::{Event, EventReader, EventWriter}, system::Query}};
+
+use crate::{gameplay::react::React, health::{Health, HealthAction, HealthActionResponse}};
+
+/// Sent by gameplay to have a `HealthAction` executed on the `Health` of an entity
+#[derive(Event, Debug, Clone, Copy)]
+pub struct HealthActionEvent {
+    pub entity: Entity,
+    pub action: HealthAction,
+}
+
+/// Sent after a `HealthAction` changed something, with everything it did
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct HealthChanged {
+    pub entity: Entity,
+    pub responses: Vec<HealthActionResponse>,
+}
+
+/// Sent when the last Hit Die of an entity empties or shatters
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Died {
+    pub entity: Entity,
+}
+
+pub fn apply_health_actions(
+    mut actions: EventReader<HealthActionEvent>,
+    mut healths: Query<&mut Health>,
+    mut changed: EventWriter<HealthChanged>,
+    mut died: EventWriter<Died>,
+) {
+    for &HealthActionEvent { entity, action } in actions.read() {
+        let Ok(mut health) = healths.get_mut(entity) else { continue; };
+
+        let was_alive = !health.is_dead();
+        let responses = health.execute(action);
+
+        if responses.iter().any(|response| *response != HealthActionResponse::None) {
+            changed.send(HealthChanged { entity, responses });
+        }
+
+        if was_alive && health.is_dead() {
+            died.send(Died { entity });
+        }
+    }
+}
+
+pub struct SvarogHealthPlugin;
+
+impl Plugin for SvarogHealthPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_event::<HealthActionEvent>()
+            .add_event::<HealthChanged>()
+            .add_event::<Died>()
+            .add_systems(Update, apply_health_actions);
+    }
+}
+
+#[cfg(test)]
+mod health_events_testing {
+    use bevy::{app::App, ecs::event::Events};
+
+    use crate::{gameplay::react::React, health::{Health, HealthAction, HealthActionResponse}};
+
+    use super::{Died, HealthActionEvent, HealthChanged, SvarogHealthPlugin};
+
+    #[test]
+    fn test_actions_publish_changes_and_death() {
+
+        // [..][...] 2+3   -CHIP 1->   [..][..x] 2+2   -CHIP 10->   [xx][xxx] 0+0
+
+        let mut app = App::new();
+        app.add_plugins(SvarogHealthPlugin);
+
+        let mut health = Health::default();
+        let _ = health.execute(HealthAction::Create(2));
+        let _ = health.execute(HealthAction::Create(3));
+        let entity = app.world.spawn(health).id();
+
+        app.world.send_event(HealthActionEvent { entity, action: HealthAction::Chip(1) });
+        app.update();
+
+        let changed = app.world.resource::<Events<HealthChanged>>();
+        let mut reader = changed.get_reader();
+        assert_eq!(reader.read(changed).cloned().collect::<Vec<_>>(), vec![
+            HealthChanged { entity, responses: vec![ HealthActionResponse::ChipResponse(1, 0) ] }
+        ]);
+        assert!(app.world.resource::<Events<Died>>().is_empty());
+
+        app.world.send_event(HealthActionEvent { entity, action: HealthAction::Chip(10) });
+        app.update();
+
+        let died = app.world.resource::<Events<Died>>();
+        let mut reader = died.get_reader();
+        assert_eq!(reader.read(died).copied().collect::<Vec<_>>(), vec![ Died { entity } ]);
+        assert_eq!(app.world.get::<Health>(entity).unwrap().hit_dice.len(), 2);
+    }
+}
@@ -1,5 +1,9 @@
 pub mod health;
+pub mod health_bar;
+pub mod health_events;
 pub mod gameplay;
+pub mod save;
+pub mod creatures;
 
 use bevy::ecs::component::Component;
 use bevy::ecs::schedule::OnEnter;
@@ -12,11 +16,17 @@ use bevy::transform::components::Transform;
 use bevy::{asset::Handle, sprite::TextureAtlas};
 use bevy_asset_loader::asset_collection::AssetCollection;
 use bevy::{app::Update, ecs::{schedule::{common_conditions::in_state, IntoSystemConfigs}, 
-    system::{Commands, Local, Res, ResMut}}, input::{keyboard::KeyCode, Input}};
+    event::EventReader, system::{Commands, Local, Res, ResMut}}};
 
 use gameplay::random::{Random, Coin, SvarogRandomPlugin};
+use gameplay::turns::SvarogTurnPlugin;
+use save::SvarogSavePlugin;
+use health_bar::SvarogHealthBarPlugin;
+use health_events::SvarogHealthPlugin;
+use creatures::SvarogCreaturePlugin;
 use noisy_bevy::simplex_noise_2d_seeded;
 
+use svarog_engine::actions::ActionEvent;
 use svarog_engine::loading::{GridEditor, Fonts, Grids, SvarogStates, SvarogTextureAtlases, Tilesets};
 use svarog_engine::Svarog;
 
@@ -54,12 +64,17 @@ pub struct TextureAtlases {
 #[derive(Resource)]
 pub struct Seed(pub i32);
 
-pub fn load_static_data(tilesets: &mut Tilesets, fonts: &mut Fonts, grids: &mut Grids) {
-    tilesets.add("tilesets.csv", fonts);
+pub fn load_static_data(tilesets: &mut Tilesets, _fonts: &mut Fonts, grids: &mut Grids) {
+    tilesets.add("tilesets.csv");
     grids.add("grids.csv");
 }
 
-pub fn draw_ground(mut commands: Commands, mut grids: ResMut<Grids>) {
+pub fn draw_ground(mut commands: Commands, mut grids: ResMut<Grids>, mut drawn: Local<bool>) {
+    if *drawn {
+        return;
+    }
+
+    *drawn = true;
     let mut grid = GridEditor::new(&mut commands, &mut grids);
 
     for i in 0..200 {
@@ -82,10 +97,11 @@ pub fn draw_ground(mut commands: Commands, mut grids: ResMut<Grids>) {
     grid.set("tiles", 103, 101, "hero3");
 }
 
-pub fn change_random_updates(input: Res<Input<KeyCode>>, mut commands: Commands, mut grids: ResMut<Grids>, mut seed: ResMut<Seed>, mut first: Local<bool>) {
+pub fn change_random_updates(mut actions: EventReader<ActionEvent>, mut commands: Commands, mut grids: ResMut<Grids>, mut seed: ResMut<Seed>, mut first: Local<bool>) {
     let mut grid = GridEditor::new(&mut commands, &mut grids);
 
-    if input.just_pressed(KeyCode::Space) || !*first {
+    let regenerate = actions.read().any(|event| event.is("regenerate"));
+    if regenerate || !*first {
         *first = true;
         seed.0 += 1;
         grid.frame("ui_topleft", 0, 0, 50, 5);
@@ -103,6 +119,11 @@ pub fn main() {
         .as_bevy()
         .insert_resource(Seed(1))
         .add_plugins(SvarogRandomPlugin)
+        .add_plugins(SvarogTurnPlugin)
+        .add_plugins(SvarogSavePlugin)
+        .add_plugins(SvarogHealthPlugin)
+        .add_plugins(SvarogHealthBarPlugin)
+        .add_plugins(SvarogCreaturePlugin)
         .add_systems(OnEnter(GameStates::Game), |mut commands: Commands, textures: Res<TextureAtlases>, mut grids: ResMut<Grids>| {
             let mut grid = GridEditor::new(&mut commands, &mut grids);
 
@@ -0,0 +1,214 @@
+use std::{collections::HashMap, fmt::Display, fs, path::{Path, PathBuf}};
+
+use bevy::{app::{Last, Plugin}, ecs::{component::Component, entity::Entity, event::{Event, Events}, world::World}, log::error};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use svarog_engine::{loading::Grids, snapshot::GridSnapshot};
+
+use crate::{gameplay::{random::Random, turns::GameClock}, health::Health};
+
+/// Marks an entity whose components get saved, and tells it apart from others when loading
+#[derive(Component, Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SaveId(pub String);
+
+/// Everything that goes into a save file
+#[derive(Serialize, Deserialize)]
+pub struct SaveGame {
+    pub clock: GameClock,
+    pub random: Option<Random>,
+    pub grids: Vec<GridSnapshot>,
+    pub health: Vec<(SaveId, Health)>,
+}
+
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Ron(ron::Error),
+}
+
+impl Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Io(error) => write!(f, "Could not access save file: {}", error),
+            SaveError::Ron(error) => write!(f, "Could not read or write save: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<std::io::Error> for SaveError {
+    fn from(error: std::io::Error) -> Self {
+        SaveError::Io(error)
+    }
+}
+
+impl From<ron::Error> for SaveError {
+    fn from(error: ron::Error) -> Self {
+        SaveError::Ron(error)
+    }
+}
+
+impl SaveGame {
+    pub fn capture(world: &mut World) -> Self {
+        let mut health = world.query::<(&SaveId, &Health)>()
+            .iter(world)
+            .map(|(id, health)| (id.clone(), health.clone()))
+            .collect::<Vec<_>>();
+        health.sort_by(|a, b| a.0.cmp(&b.0));
+
+        Self {
+            clock: world.get_resource::<GameClock>().copied().unwrap_or_default(),
+            random: world.get_resource::<Random>().cloned(),
+            grids: world.get_resource::<Grids>().map(|grids| grids.snapshot()).unwrap_or_default(),
+            health,
+        }
+    }
+
+    /// Puts the saved state into a world. Entities with a matching `SaveId` get their components replaced,
+    /// and saved entities that aren't around get spawned.
+    pub fn apply(self, world: &mut World) {
+        world.insert_resource(self.clock);
+        if let Some(random) = self.random {
+            world.insert_resource(random);
+        }
+
+        if let Some(mut grids) = world.get_resource_mut::<Grids>() {
+            grids.restore(&self.grids);
+        }
+
+        let existing = world.query::<(Entity, &SaveId)>()
+            .iter(world)
+            .map(|(entity, id)| (id.clone(), entity))
+            .collect::<HashMap<_, _>>();
+
+        for (id, health) in self.health {
+            match existing.get(&id) {
+                Some(&entity) => { world.entity_mut(entity).insert(health); },
+                None => { world.spawn((id, health)); },
+            }
+        }
+    }
+}
+
+pub fn save_game(world: &mut World, path: &Path) -> Result<(), SaveError> {
+    let save = SaveGame::capture(world);
+    fs::write(path, ron::ser::to_string_pretty(&save, PrettyConfig::default())?)?;
+    Ok(())
+}
+
+pub fn load_game(world: &mut World, path: &Path) -> Result<(), SaveError> {
+    let save: SaveGame = ron::de::from_str(&fs::read_to_string(path)?)?;
+    save.apply(world);
+    Ok(())
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct SaveRequested(pub PathBuf);
+
+#[derive(Event, Debug, Clone)]
+pub struct LoadRequested(pub PathBuf);
+
+/// Sent when a requested save couldn't be written, with what went wrong
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct SaveFailed {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+/// Sent when a requested save couldn't be read or applied, with what went wrong
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct LoadFailed {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+pub fn handle_save_requests(world: &mut World) {
+    let saves = world.resource_mut::<Events<SaveRequested>>().drain().collect::<Vec<_>>();
+    for SaveRequested(path) in saves {
+        if let Err(error) = save_game(world, &path) {
+            error!("Could not save to {}: {}", path.display(), error);
+            world.send_event(SaveFailed { path, message: error.to_string() });
+        }
+    }
+
+    let loads = world.resource_mut::<Events<LoadRequested>>().drain().collect::<Vec<_>>();
+    for LoadRequested(path) in loads {
+        if let Err(error) = load_game(world, &path) {
+            error!("Could not load {}: {}", path.display(), error);
+            world.send_event(LoadFailed { path, message: error.to_string() });
+        }
+    }
+}
+
+/// Saves and loads the game when `SaveRequested` and `LoadRequested` are sent
+pub struct SvarogSavePlugin;
+
+impl Plugin for SvarogSavePlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_event::<SaveRequested>()
+            .add_event::<LoadRequested>()
+            .add_event::<SaveFailed>()
+            .add_event::<LoadFailed>()
+            .add_systems(Last, handle_save_requests);
+    }
+}
+
+#[cfg(test)]
+mod save_testing {
+    use std::path::PathBuf;
+
+    use bevy::{app::App, ecs::event::Events};
+
+    use crate::{gameplay::{react::React, turns::GameClock}, health::{Health, HealthAction, HitDieStatus}};
+
+    use super::{load_game, save_game, LoadFailed, LoadRequested, SaveId, SvarogSavePlugin};
+
+    /// A path in the temp folder no other test run uses, so runs side by side don't clash
+    fn temp_path(name: &str) -> PathBuf {
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|time| time.as_nanos()).unwrap_or_default();
+        std::env::temp_dir().join(format!("svarog_{}_{}_{}.ron", name, std::process::id(), nanos))
+    }
+
+    #[test]
+    fn test_save_and_load_into_fresh_app() {
+        let mut app = App::new();
+        app.insert_resource(GameClock { time: 42, turns: 7 });
+
+        let mut health = Health::default();
+        let _ = health.execute(HealthAction::Create(6));
+        let _ = health.execute(HealthAction::Chip(2));
+        let _ = health.execute(HealthAction::AddStatus(HitDieStatus::Cracked(3)));
+        app.world.spawn((SaveId("hero".to_string()), health));
+
+        let path = temp_path("save_testing");
+        save_game(&mut app.world, &path).unwrap();
+
+        let mut fresh = App::new();
+        load_game(&mut fresh.world, &path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(*fresh.world.resource::<GameClock>(), GameClock { time: 42, turns: 7 });
+
+        let mut query = fresh.world.query::<(&SaveId, &Health)>();
+        let (id, health) = query.single(&fresh.world);
+        assert_eq!(id.0, "hero");
+        assert_eq!(*health.hit_dice[0].value, 4);
+        assert!(health.hit_dice[0].statuses.contains(&HitDieStatus::Cracked(3)));
+    }
+
+    #[test]
+    fn test_failed_load_is_sent() {
+        let mut app = App::new();
+        app.add_plugins(SvarogSavePlugin);
+
+        let path = temp_path("missing_save");
+        app.world.send_event(LoadRequested(path.clone()));
+        app.update();
+
+        let failed = app.world.resource_mut::<Events<LoadFailed>>().drain().collect::<Vec<_>>();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].path, path);
+    }
+}
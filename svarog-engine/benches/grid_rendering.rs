@@ -0,0 +1,203 @@
+//! Compares drawing a big glyph grid the way it was done before chunks, with a sprite per cell spawned up front,
+//! against streaming chunks in around the camera. Both redraw the same changed cell every frame.
+//! Run with `cargo bench --bench grid_rendering`.
+
+use std::time::{Duration, Instant};
+
+use bevy::{app::{App, Update}, asset::Handle, ecs::{component::Component, entity::Entity, query::{With, Without}, system::{CommandQueue, Commands, Local, Query, Res, ResMut, Resource}},
+    hierarchy::BuildChildren, math::{Vec2, Vec3}, render::{camera::OrthographicProjection, color::Color, view::{Visibility, VisibilityBundle}},
+    sprite::{Sprite, SpriteBundle, SpriteSheetBundle, TextureAtlas, TextureAtlasSprite}, transform::{components::{GlobalTransform, Transform}, TransformBundle}, window::{PrimaryWindow, Window}};
+use svarog_engine::{errors::Reported, loading::{strings, CameraTag, Cell, Font, Fonts, Glyph, Grid, GridKind, Grids, Tileset, Tilesets}, update::{cell_look, grid_stream_chunks, grid_update_values}, windows::SvarogScale};
+
+const SIZE: i32 = 1000;
+const FRAMES: u32 = 100;
+
+fn resources() -> (Tilesets, Fonts, Grids) {
+    let mut tilesets = Tilesets::default();
+    tilesets.tilesets.insert("bench".to_string(), Tileset {
+        name: "bench".to_string(),
+        font: "bench.font.csv".to_string(),
+        weight: 0,
+        width: 16,
+        height: 16,
+        columns: 16,
+        rows: 16,
+    });
+
+    let mut font = Font::default();
+    font.glyphs.insert("#".to_string(), Glyph { name: "#".to_string(), x: 4, y: 3, attributes: vec![] });
+    let mut fonts = Fonts::default();
+    fonts.fonts.insert("bench.font.csv".to_string(), font);
+
+    let mut grid = Grid {
+        name: "world".to_string(),
+        width: SIZE,
+        height: SIZE,
+        kind: GridKind::Glyph,
+        tileset: "bench".to_string(),
+        ..Default::default()
+    };
+    grid.allocate();
+
+    let cell = {
+        let mut strings = strings().lock().unwrap();
+        Cell { tileset: strings.pass("bench"), glyph: strings.pass("#"), ..Default::default() }
+    };
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            grid.put(x, y, cell);
+        }
+    }
+
+    let mut grids = Grids::default();
+    grids.grids.insert("world".to_string(), grid);
+    (tilesets, fonts, grids)
+}
+
+fn app() -> App {
+    let (tilesets, fonts, grids) = resources();
+    let mut app = App::new();
+    app.insert_resource(tilesets).insert_resource(fonts).insert_resource(grids).init_resource::<SvarogScale>().init_resource::<Reported>();
+    app.world.spawn((Window::default(), PrimaryWindow));
+    app.world.spawn((CameraTag, Transform::default(), GlobalTransform::default(), OrthographicProjection::default()));
+    app
+}
+
+/// Every frame changes a single cell and moves the camera a little
+fn scroll(mut grids: ResMut<Grids>, mut camera: Query<(&mut Transform, &mut GlobalTransform), With<CameraTag>>, mut frame: Local<i32>) {
+    *frame += 1;
+    grids.set_foreground("world", *frame % SIZE, *frame % SIZE, Color::rgb(0.5, 0.5, 0.5));
+    for (mut transform, mut global) in &mut camera {
+        transform.translation += Vec3::new(8.0, -8.0, 0.0);
+        *global = GlobalTransform::from(*transform);
+    }
+}
+
+/// Points from a glyph sprite to the plain sprite drawn behind it, as the per-cell renderer did
+#[derive(Component)]
+struct CellBackground(Entity);
+
+/// Glyph sprite of every cell, indexed like `Grid::cells`
+#[derive(Resource, Default)]
+struct CellSprites(Vec<Entity>);
+
+/// The renderer glyph grids had before chunks: a sprite and a background sprite for every cell, all spawned up front
+fn spawn_grid_sprites(commands: &mut Commands, grid: &Grid, tileset: &Tileset, atlas: Handle<TextureAtlas>) -> Vec<Entity> {
+    let mut entities = Vec::with_capacity(grid.cells.len());
+    commands.spawn((TransformBundle::default(), VisibilityBundle::default()))
+        .with_children(|f| {
+            for j in 0..grid.height {
+                for i in 0..grid.width {
+                    let position = Vec3::new(((grid.x + i) * tileset.width) as f32, ((grid.y + j) * tileset.height) as f32, grid.depth as f32);
+
+                    let background = f.spawn(SpriteBundle {
+                        sprite: Sprite { custom_size: Some(Vec2::new(tileset.width as f32, tileset.height as f32)), ..Default::default() },
+                        transform: Transform::from_translation(position - Vec3::Z * 0.5),
+                        visibility: Visibility::Hidden,
+                        ..Default::default()
+                    }).id();
+
+                    let handle = f.spawn((SpriteSheetBundle {
+                        sprite: TextureAtlasSprite { index: 0, ..Default::default() },
+                        texture_atlas: atlas.clone(),
+                        transform: Transform::from_translation(position),
+                        visibility: Visibility::Hidden,
+                        ..Default::default()
+                    }, CellBackground(background))).id();
+
+                    entities.push(handle);
+                }
+            }
+        });
+    entities
+}
+
+/// The per-cell renderer's update: looks up the sprites of dirty cells through `CellBackground` and redraws them
+fn per_cell_update_values(
+    mut grids: ResMut<Grids>,
+    tilesets: Res<Tilesets>,
+    fonts: Res<Fonts>,
+    sprites: Res<CellSprites>,
+    mut glyph_query: Query<(&mut TextureAtlasSprite, &mut Visibility, Option<&CellBackground>)>,
+    mut background_query: Query<(&mut Sprite, &mut Visibility), Without<TextureAtlasSprite>>,
+    mut reported: ResMut<Reported>,
+) {
+    let blinked = grids.blinked;
+    let mut strings = strings().lock().unwrap();
+    for grid in grids.grids.values_mut() {
+        let dirty = std::mem::take(&mut grid.dirty);
+        for index in dirty {
+            let Some(cell) = grid.cells.get(index) else { continue; };
+            let Some(entity) = sprites.0.get(index) else { continue; };
+            let Ok((mut sprite, mut visibility, cell_background)) = glyph_query.get_mut(*entity) else { continue; };
+
+            let fog = grid.fog.get(index).copied().unwrap_or_default();
+            let look = cell_look(cell, fog, grid.remembered, blinked, &mut strings, &tilesets, &fonts, &mut reported);
+            *visibility = if look.shown { Visibility::Visible } else { Visibility::Hidden };
+            sprite.color = look.foreground;
+            if let Some(index) = look.index {
+                sprite.index = index;
+            }
+
+            if let Some(Ok((mut background_sprite, mut background_visibility))) = cell_background.map(|b| background_query.get_mut(b.0)) {
+                *background_visibility = if look.background.is_some() { Visibility::Visible } else { Visibility::Hidden };
+                if let Some(color) = look.background {
+                    background_sprite.color = color;
+                }
+            }
+        }
+    }
+}
+
+fn per_cell() -> (Duration, Duration) {
+    let mut app = app();
+
+    let start = Instant::now();
+    let mut queue = CommandQueue::default();
+    let mut commands = Commands::new(&mut queue, &app.world);
+    let (tilesets, grids) = (app.world.resource::<Tilesets>(), app.world.resource::<Grids>());
+    let entities = spawn_grid_sprites(&mut commands, &grids.grids["world"], &tilesets.tilesets["bench"], Handle::default());
+    queue.apply(&mut app.world);
+    app.insert_resource(CellSprites(entities));
+    app.add_systems(Update, (scroll, per_cell_update_values).chain());
+    app.update();
+    let setup = start.elapsed();
+
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        app.update();
+    }
+    (setup, start.elapsed() / FRAMES)
+}
+
+fn chunked() -> (Duration, Duration) {
+    let mut app = app();
+
+    let start = Instant::now();
+    let parent = app.world.spawn((Transform::default(), GlobalTransform::default())).id();
+    {
+        let mut grids = app.world.resource_mut::<Grids>();
+        let grid = grids.grids.get_mut("world").unwrap();
+        grid.entity = Some(parent);
+        grid.atlas = Some(Handle::default());
+    }
+    app.add_systems(Update, (scroll, grid_stream_chunks, grid_update_values).chain());
+    app.update();
+    let setup = start.elapsed();
+
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        app.update();
+    }
+    (setup, start.elapsed() / FRAMES)
+}
+
+fn main() {
+    println!("{}x{} grid, {} frames", SIZE, SIZE, FRAMES);
+
+    let (setup, frame) = chunked();
+    println!("chunked:  setup {:>10.2?}, frame {:>10.2?}", setup, frame);
+
+    let (setup, frame) = per_cell();
+    println!("per cell: setup {:>10.2?}, frame {:>10.2?}", setup, frame);
+}
@@ -0,0 +1,335 @@
+use std::{collections::{BTreeMap, HashMap, HashSet}, fs, io::ErrorKind, path::Path};
+
+use bevy::{app::{Plugin, PreUpdate}, ecs::{event::{Event, EventWriter}, schedule::IntoSystemConfigs, system::{Local, Res, ResMut, Resource}},
+    input::{gamepad::{GamepadButton, GamepadButtonType, Gamepads}, keyboard::KeyCode, Input, InputSystem}, time::Time};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::SvarogConfig, errors::{report_on, Reported, SvarogError}};
+
+pub const DEFAULT_ACTIONS_PATH: &str = "actions.ron";
+
+/// What a binding listens to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+    Key(KeyCode),
+    Gamepad(GamepadButtonType),
+}
+
+/// A key or gamepad button an action is bound to. Keys bound with `Shift+` only count while shift is held,
+/// and keys bound without it only while it isn't.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Binding {
+    pub button: Button,
+    pub shift: bool,
+}
+
+/// Sent for an action when one of its bindings is pressed, and again every so often while it's held if the action repeats
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct ActionEvent {
+    pub action: String,
+    pub repeat: bool,
+}
+
+impl ActionEvent {
+    pub fn is(&self, action: &str) -> bool {
+        self.action == action
+    }
+}
+
+/// The contents of `actions.ron`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, rename = "Actions")]
+pub struct ActionFile {
+    /// Seconds an action has to be held before it starts repeating
+    pub repeat_delay: f32,
+    /// Seconds between repeats after that
+    pub repeat_interval: f32,
+    /// Actions that repeat while held, like movement; the rest fire once per press
+    pub repeating: Vec<String>,
+    /// Key and gamepad button names bound to each action, e.g. `Numpad8`, `Shift+Comma` or `Pad:South`
+    pub bindings: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for ActionFile {
+    fn default() -> Self {
+        Self { repeat_delay: 0.3, repeat_interval: 0.1, repeating: Vec::new(), bindings: BTreeMap::new() }
+    }
+}
+
+/// Actions with what they're bound to, built from `actions.ron` and the `keybindings` of the config
+#[derive(Resource, Debug, Clone, PartialEq, Default)]
+pub struct ActionMap {
+    pub actions: BTreeMap<String, Vec<Binding>>,
+    pub repeating: HashSet<String>,
+    pub repeat_delay: f32,
+    pub repeat_interval: f32,
+}
+
+/// How long until each held action fires again
+#[derive(Resource, Debug, Clone, Default)]
+pub struct ActionRepeat(pub HashMap<String, f32>);
+
+pub fn key_code(name: &str) -> Option<KeyCode> {
+    use KeyCode::*;
+
+    Some(match name {
+        "A" => A, "B" => B, "C" => C, "D" => D, "E" => E, "F" => F, "G" => G, "H" => H, "I" => I,
+        "J" => J, "K" => K, "L" => L, "M" => M, "N" => N, "O" => O, "P" => P, "Q" => Q, "R" => R,
+        "S" => S, "T" => T, "U" => U, "V" => V, "W" => W, "X" => X, "Y" => Y, "Z" => Z,
+        "Key0" => Key0, "Key1" => Key1, "Key2" => Key2, "Key3" => Key3, "Key4" => Key4,
+        "Key5" => Key5, "Key6" => Key6, "Key7" => Key7, "Key8" => Key8, "Key9" => Key9,
+        "Numpad0" => Numpad0, "Numpad1" => Numpad1, "Numpad2" => Numpad2, "Numpad3" => Numpad3, "Numpad4" => Numpad4,
+        "Numpad5" => Numpad5, "Numpad6" => Numpad6, "Numpad7" => Numpad7, "Numpad8" => Numpad8, "Numpad9" => Numpad9,
+        "NumpadAdd" => NumpadAdd, "NumpadSubtract" => NumpadSubtract, "NumpadMultiply" => NumpadMultiply,
+        "NumpadDivide" => NumpadDivide, "NumpadDecimal" => NumpadDecimal, "NumpadEnter" => NumpadEnter,
+        "F1" => F1, "F2" => F2, "F3" => F3, "F4" => F4, "F5" => F5, "F6" => F6,
+        "F7" => F7, "F8" => F8, "F9" => F9, "F10" => F10, "F11" => F11, "F12" => F12,
+        "Up" => Up, "Down" => Down, "Left" => Left, "Right" => Right,
+        "Home" => Home, "End" => End, "PageUp" => PageUp, "PageDown" => PageDown, "Insert" => Insert, "Delete" => Delete,
+        "Space" => Space, "Return" | "Enter" => Return, "Escape" => Escape, "Tab" => Tab, "Back" | "Backspace" => Back,
+        "Comma" => Comma, "Period" => Period, "Slash" => Slash, "Backslash" => Backslash, "Semicolon" => Semicolon,
+        "Apostrophe" => Apostrophe, "Minus" => Minus, "Equals" => Equals, "Grave" => Grave,
+        "BracketLeft" => BracketLeft, "BracketRight" => BracketRight,
+        _ => return None,
+    })
+}
+
+pub fn gamepad_button(name: &str) -> Option<GamepadButtonType> {
+    use GamepadButtonType::*;
+
+    Some(match name {
+        "South" => South, "East" => East, "North" => North, "West" => West,
+        "DPadUp" => DPadUp, "DPadDown" => DPadDown, "DPadLeft" => DPadLeft, "DPadRight" => DPadRight,
+        "LeftTrigger" => LeftTrigger, "LeftTrigger2" => LeftTrigger2, "RightTrigger" => RightTrigger, "RightTrigger2" => RightTrigger2,
+        "LeftThumb" => LeftThumb, "RightThumb" => RightThumb, "Select" => Select, "Start" => Start, "Mode" => Mode,
+        _ => return None,
+    })
+}
+
+/// Reads a binding the way it's written in `actions.ron` and `keybindings`
+pub fn parse_binding(name: &str) -> Option<Binding> {
+    let name = name.trim();
+    if let Some(button) = name.strip_prefix("Pad:") {
+        return gamepad_button(button.trim()).map(|button| Binding { button: Button::Gamepad(button), shift: false });
+    }
+
+    let (key, shift) = match name.strip_prefix("Shift+") {
+        Some(key) => (key.trim(), true),
+        None => (name, false),
+    };
+    key_code(key).map(|key| Binding { button: Button::Key(key), shift })
+}
+
+impl ActionMap {
+    /// Builds the map from an action file, with `overrides` replacing the bindings of the actions they name.
+    /// Bindings that aren't known keys or gamepad buttons are left out and returned as errors.
+    pub fn new(file: &ActionFile, overrides: &BTreeMap<String, Vec<String>>) -> (Self, Vec<SvarogError>) {
+        let mut bindings = file.bindings.clone();
+        bindings.extend(overrides.iter().map(|(action, names)| (action.clone(), names.clone())));
+
+        let mut errors = vec![];
+        let actions = bindings.into_iter().map(|(action, names)| {
+            let parsed = names.iter().filter_map(|name| {
+                let binding = parse_binding(name);
+                if binding.is_none() {
+                    errors.push(SvarogError::UnknownBinding { action: action.clone(), binding: name.clone() });
+                }
+                binding
+            }).collect();
+            (action, parsed)
+        }).collect();
+
+        (Self {
+            actions,
+            repeating: file.repeating.iter().cloned().collect(),
+            repeat_delay: file.repeat_delay,
+            repeat_interval: file.repeat_interval,
+        }, errors)
+    }
+
+    /// Reads the action file like the config is read: missing means no actions, and a broken one is returned as an error
+    pub fn load(path: &Path, overrides: &BTreeMap<String, Vec<String>>) -> (Self, Vec<SvarogError>) {
+        let (file, error) = match fs::read_to_string(path) {
+            Ok(text) => match ron::de::from_str::<ActionFile>(&text) {
+                Ok(file) => (file, None),
+                Err(error) => (ActionFile::default(), Some(SvarogError::Config { path: path.display().to_string(), message: error.to_string() })),
+            },
+            Err(error) if error.kind() == ErrorKind::NotFound => (ActionFile::default(), None),
+            Err(error) => (ActionFile::default(), Some(SvarogError::Io { path: path.display().to_string(), error })),
+        };
+
+        let (map, errors) = Self::new(&file, overrides);
+        (map, error.into_iter().chain(errors).collect())
+    }
+
+    /// Works out which actions fire this frame, given which bindings are down and how long the frame took
+    pub fn fire(&self, is_down: impl Fn(&Binding) -> bool, repeat: &mut ActionRepeat, delta: f32) -> Vec<ActionEvent> {
+        let mut events = Vec::new();
+
+        for (action, bindings) in &self.actions {
+            if !bindings.iter().any(&is_down) {
+                repeat.0.remove(action);
+                continue;
+            }
+
+            match repeat.0.get_mut(action) {
+                None => {
+                    repeat.0.insert(action.clone(), self.repeat_delay);
+                    events.push(ActionEvent { action: action.clone(), repeat: false });
+                },
+                Some(left) if self.repeating.contains(action) => {
+                    *left -= delta;
+                    // no catching up after a long frame, so a hitch doesn't turn into several moves
+                    if *left <= 0.0 {
+                        *left = self.repeat_interval;
+                        events.push(ActionEvent { action: action.clone(), repeat: true });
+                    }
+                },
+                Some(_) => {},
+            }
+        }
+
+        events
+    }
+}
+
+pub fn read_actions(
+    map: Res<ActionMap>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    gamepads: Res<Gamepads>,
+    time: Res<Time>,
+    mut repeat: ResMut<ActionRepeat>,
+    mut events: EventWriter<ActionEvent>,
+) {
+    let shift = keys.any_pressed([ KeyCode::ShiftLeft, KeyCode::ShiftRight ]);
+    let is_down = |binding: &Binding| match binding.button {
+        Button::Key(key) => keys.pressed(key) && binding.shift == shift,
+        Button::Gamepad(button) => gamepads.iter().any(|gamepad| buttons.pressed(GamepadButton::new(gamepad, button))),
+    };
+
+    events.send_batch(map.fire(is_down, &mut repeat, time.delta_seconds()));
+}
+
+/// Rebuilds the action map when the action file or the keybindings in the config change, and not when some
+/// other setting does. The map the plugin built counts as made from the config as it is on the first run.
+pub fn follow_keybindings(
+    config: Res<SvarogConfig>,
+    mut map: ResMut<ActionMap>,
+    mut reported: ResMut<Reported>,
+    mut last: Local<Option<(String, BTreeMap<String, Vec<String>>)>>,
+) {
+    if !config.is_changed() {
+        return;
+    }
+
+    if let Some((actions, keybindings)) = last.as_ref() {
+        if *actions == config.actions && *keybindings == config.keybindings {
+            return;
+        }
+
+        let (rebuilt, errors) = ActionMap::load(Path::new(&config.actions), &config.keybindings);
+        reported.extend(errors);
+        map.set_if_neq(rebuilt);
+    }
+
+    *last = Some((config.actions.clone(), config.keybindings.clone()));
+}
+
+/// Turns keys and gamepad buttons into `ActionEvent`s, as bound in the action file named by the config
+pub struct SvarogActionPlugin;
+
+impl Plugin for SvarogActionPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        let (map, errors) = match app.world.get_resource::<SvarogConfig>() {
+            Some(config) => ActionMap::load(Path::new(&config.actions), &config.keybindings),
+            None => ActionMap::load(Path::new(DEFAULT_ACTIONS_PATH), &BTreeMap::new()),
+        };
+
+        report_on(app, errors);
+        app.insert_resource(map)
+            .init_resource::<ActionRepeat>()
+            .add_event::<ActionEvent>()
+            .add_systems(PreUpdate, (follow_keybindings, read_actions).chain().after(InputSystem));
+    }
+}
+
+#[cfg(test)]
+mod actions_testing {
+    use std::collections::BTreeMap;
+
+    use bevy::{app::{App, Update}, input::{gamepad::GamepadButtonType, keyboard::KeyCode}};
+
+    use crate::{config::SvarogConfig, errors::Reported};
+
+    use super::{follow_keybindings, parse_binding, ActionEvent, ActionFile, ActionMap, ActionRepeat, Binding, Button};
+
+    fn map() -> ActionMap {
+        let file: ActionFile = ron::de::from_str(r#"Actions(
+            repeat_delay: 0.25,
+            repeat_interval: 0.15,
+            repeating: [ "move_n" ],
+            bindings: {
+                "move_n": [ "Up", "Numpad8", "K", "Pad:DPadUp" ],
+                "pick_up": [ "G" ],
+            },
+        )"#).unwrap();
+
+        let mut overrides = BTreeMap::new();
+        overrides.insert("pick_up".to_string(), vec![ "Comma".to_string() ]);
+        ActionMap::new(&file, &overrides).0
+    }
+
+    fn key(key: KeyCode) -> Binding {
+        Binding { button: Button::Key(key), shift: false }
+    }
+
+    #[test]
+    fn test_parse_bindings() {
+        assert_eq!(parse_binding("Numpad8"), Some(key(KeyCode::Numpad8)));
+        assert_eq!(parse_binding("Shift+Comma"), Some(Binding { button: Button::Key(KeyCode::Comma), shift: true }));
+        assert_eq!(parse_binding("Pad:South"), Some(Binding { button: Button::Gamepad(GamepadButtonType::South), shift: false }));
+        assert_eq!(parse_binding("Pad:Space"), None);
+        assert_eq!(parse_binding("Hyper"), None);
+    }
+
+    #[test]
+    fn test_overrides_replace_bindings() {
+        let map = map();
+        assert_eq!(map.actions["pick_up"], vec![ key(KeyCode::Comma) ]);
+        assert_eq!(map.actions["move_n"].len(), 4);
+    }
+
+    #[test]
+    fn test_held_actions_repeat_after_delay() {
+        // move_n held for 0.7s at 0.1s per frame: fires at once, then after the delay, then every interval
+        let map = map();
+        let mut repeat = ActionRepeat::default();
+        let down = |binding: &Binding| *binding == key(KeyCode::K) || *binding == key(KeyCode::Comma);
+        let frames = (0..7).map(|_| map.fire(down, &mut repeat, 0.1)).collect::<Vec<_>>();
+        let moves = frames.iter().map(|events| events.iter().filter(|event| event.is("move_n")).count()).collect::<Vec<_>>();
+        assert_eq!(moves, vec![ 1, 0, 0, 1, 0, 1, 0 ]);
+        assert_eq!(frames[0][0], ActionEvent { action: "move_n".to_string(), repeat: false });
+        assert!(frames[3][0].repeat);
+        let pick_ups = frames.iter().flatten().filter(|event| event.is("pick_up")).count();
+        assert_eq!(pick_ups, 1);
+        assert!(map.fire(|_| false, &mut repeat, 0.1).is_empty());
+        assert_eq!(map.fire(down, &mut repeat, 0.1).len(), 2);
+    }
+
+    #[test]
+    fn test_map_is_rebuilt_only_when_bindings_change() {
+        let mut app = App::new();
+        app.insert_resource(SvarogConfig::default()).insert_resource(map()).init_resource::<Reported>()
+            .add_systems(Update, follow_keybindings);
+        app.update();
+
+        app.world.resource_mut::<SvarogConfig>().volume = 0.5;
+        app.update();
+        assert_eq!(*app.world.resource::<ActionMap>(), map());
+
+        app.world.resource_mut::<SvarogConfig>().keybindings.insert("pick_up".to_string(), vec![ "G".to_string() ]);
+        app.update();
+        assert_ne!(*app.world.resource::<ActionMap>(), map());
+    }
+}
@@ -0,0 +1,184 @@
+use std::marker::PhantomData;
+
+use bevy::{app::{Plugin, PostUpdate}, ecs::{entity::Entity, query::{With, Without}, schedule::{common_conditions::in_state, IntoSystemConfigs},
+    system::{Query, Res, Resource}}, math::{Vec2, Vec3}, render::camera::OrthographicProjection, time::Time,
+    transform::{components::{GlobalTransform, Transform}, TransformSystem}, window::{PrimaryWindow, Window}};
+
+use crate::{loading::{CameraTag, Grid, GridAlign, GridTag, Grids, SvarogStates, Tilesets}, windows::SvarogScale};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraTarget {
+    /// Stay wherever the camera is
+    None,
+    /// Follow an entity's position in the world
+    Entity(Entity),
+    /// Center on a cell of the viewport grid, with `y` growing downwards like in `Grids::set`
+    Cell(i32, i32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scrolling {
+    /// Jump straight to the target, on whole pixels
+    Snap,
+    /// Ease towards the target, closing this fraction of the distance every second
+    Smooth(f32),
+}
+
+/// Drives the `CameraTag` camera over a large grid
+#[derive(Resource, Debug, Clone)]
+pub struct Viewport {
+    /// The grid whose cells `CameraTarget::Cell` refers to, and whose bounds the camera stays within
+    pub grid: String,
+    pub target: CameraTarget,
+    pub scrolling: Scrolling,
+    /// Integer zoom level: at 2, every glyph is drawn twice as big
+    pub zoom: u32,
+    pub clamp: bool,
+}
+
+impl Viewport {
+    pub fn new(grid: &str) -> Self {
+        Self {
+            grid: grid.to_string(),
+            target: CameraTarget::None,
+            scrolling: Scrolling::Snap,
+            zoom: 1,
+            clamp: true,
+        }
+    }
+
+    pub fn follow(&mut self, target: CameraTarget) {
+        self.target = target;
+    }
+
+    pub fn zoom_in(&mut self) {
+        self.zoom = (self.zoom + 1).min(8);
+    }
+
+    pub fn zoom_out(&mut self) {
+        self.zoom = self.zoom.saturating_sub(1).max(1);
+    }
+}
+
+impl Grid {
+    /// World position of the center of a cell in a grid that isn't camera aligned
+    pub fn cell_center(&self, tilesets: &Tilesets, x: i32, y: i32) -> Option<Vec2> {
+        let tileset = tilesets.tilesets.get(&self.tileset)?;
+        let j = self.height - 1 - y;
+        Some(Vec2::new(((self.x + x) * tileset.width) as f32, ((self.y + j) * tileset.height) as f32))
+    }
+
+    /// World rectangle covered by a grid that isn't camera aligned, as its min and max corners
+    pub fn bounds(&self, tilesets: &Tilesets) -> Option<(Vec2, Vec2)> {
+        let tileset = tilesets.tilesets.get(&self.tileset)?;
+        let (w, h) = (tileset.width as f32, tileset.height as f32);
+        let min = Vec2::new(self.x as f32 * w - w * 0.5, self.y as f32 * h - h * 0.5);
+        let max = min + Vec2::new(self.width as f32 * w, self.height as f32 * h);
+        Some((min, max))
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn viewport_follow(
+    viewport: Res<Viewport>,
+    grids: Res<Grids>,
+    tilesets: Res<Tilesets>,
+    time: Res<Time>,
+    pixels: Res<SvarogScale>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    targets: Query<&GlobalTransform, Without<CameraTag>>,
+    mut camera: Query<(&mut Transform, &mut OrthographicProjection), With<CameraTag>>,
+) {
+    let Ok((mut transform, mut projection)) = camera.get_single_mut() else { return; };
+    let scale = 1.0 / viewport.zoom.max(1) as f32;
+    if projection.scale != scale {
+        projection.scale = scale;
+    }
+
+    let grid = grids.grids.get(&viewport.grid);
+    let current = transform.translation.truncate();
+    let mut wanted = match viewport.target {
+        CameraTarget::None => current,
+        CameraTarget::Entity(entity) => targets.get(entity).map(|t| t.translation().truncate()).unwrap_or(current),
+        CameraTarget::Cell(x, y) => grid.and_then(|g| g.cell_center(&tilesets, x, y)).unwrap_or(current),
+    };
+
+    if viewport.clamp {
+        if let (Some((min, max)), Ok(window)) = (grid.and_then(|g| g.bounds(&tilesets)), window.get_single()) {
+            let half = pixels.view_of(window) * 0.5 * scale;
+            wanted.x = if max.x - min.x <= half.x * 2.0 { (min.x + max.x) * 0.5 } else { wanted.x.clamp(min.x + half.x, max.x - half.x) };
+            wanted.y = if max.y - min.y <= half.y * 2.0 { (min.y + max.y) * 0.5 } else { wanted.y.clamp(min.y + half.y, max.y - half.y) };
+        }
+    }
+
+    let next = match viewport.scrolling {
+        Scrolling::Snap => (wanted / scale).round() * scale,
+        Scrolling::Smooth(speed) => {
+            let t = 1.0 - (-speed * time.delta_seconds()).exp();
+            current.lerp(wanted, t)
+        },
+    };
+
+    if next != current {
+        transform.translation = next.extend(transform.translation.z);
+    }
+}
+
+/// Counteracts the camera zoom for camera-aligned grids so they stay the same size and in the same place on screen
+pub fn viewport_pin_aligned_grids(
+    viewport: Res<Viewport>,
+    grids: Res<Grids>,
+    tilesets: Res<Tilesets>,
+    pixels: Res<SvarogScale>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    mut transforms: Query<&mut Transform, (With<GridTag>, Without<CameraTag>)>,
+) {
+    let Ok(window) = window.get_single() else { return; };
+    let view = pixels.view_of(window);
+    let scale = 1.0 / viewport.zoom.max(1) as f32;
+
+    for grid in grids.grids.values() {
+        if grid.align == GridAlign::None {
+            continue;
+        }
+
+        let Some(entity) = grid.entity else { continue; };
+        let Some(tileset) = tilesets.tilesets.get(&grid.tileset) else { continue; };
+        let Some(position) = grid.align(tileset, view) else { continue; };
+        let Ok(mut transform) = transforms.get_mut(entity) else { continue; };
+
+        let pinned = Transform::from_translation(position * scale).with_scale(Vec3::splat(scale));
+        if *transform != pinned {
+            *transform = pinned;
+        }
+    }
+}
+
+pub struct SvarogCameraPlugin<S: SvarogStates> {
+    viewport: Viewport,
+    _marker: PhantomData<S>,
+}
+
+impl<S: SvarogStates> SvarogCameraPlugin<S> {
+    pub fn new(grid: &str) -> Self {
+        Self {
+            viewport: Viewport::new(grid),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn with_scrolling(mut self, scrolling: Scrolling) -> Self {
+        self.viewport.scrolling = scrolling;
+        self
+    }
+}
+
+impl<S: SvarogStates> Plugin for SvarogCameraPlugin<S> {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.insert_resource(self.viewport.clone())
+            .init_resource::<SvarogScale>();
+        app.add_systems(PostUpdate, (viewport_follow, viewport_pin_aligned_grids)
+            .before(TransformSystem::TransformPropagate)
+            .run_if(in_state(S::done_loading_state())));
+    }
+}
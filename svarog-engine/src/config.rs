@@ -0,0 +1,217 @@
+use std::{collections::BTreeMap, fs, io::ErrorKind, path::{Path, PathBuf}};
+
+use bevy::{audio::GlobalVolume, ecs::{query::With, system::{Local, Query, Res, ResMut, Resource}}, window::{PresentMode, PrimaryWindow, Window, WindowMode}};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::{actions::DEFAULT_ACTIONS_PATH, errors::SvarogError};
+
+/// Environment variable that points to the config file, if there's no `--config` argument
+pub const CONFIG_ENV: &str = "SVAROG_CONFIG";
+pub const DEFAULT_CONFIG_PATH: &str = "config.ron";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SvarogWindowMode {
+    Fullscreen,
+    Windowed(u32, u32),
+}
+
+/// Draws everything at the largest whole scale at which `columns` by `rows` cells of `tileset` fit into the window,
+/// with bars around whatever is left over
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PixelScaling {
+    pub columns: u32,
+    pub rows: u32,
+    /// The config's `tileset` if left empty
+    #[serde(default)]
+    pub tileset: String,
+}
+
+/// Settings read from the config file. It can be changed while the game runs, and the window and audio follow;
+/// `save` writes it back to the file it came from.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, rename = "Config")]
+pub struct SvarogConfig {
+    pub title: String,
+    pub mode: SvarogWindowMode,
+    pub vsync: bool,
+    /// Overrides the scale factor the OS reports for the window
+    pub scale_factor: Option<f32>,
+    /// Pixel-perfect scaling; without it, everything is drawn at its size in pixels
+    pub scaling: Option<PixelScaling>,
+    /// Tileset for grids that don't name one, or name `default`
+    pub tileset: String,
+    /// Action file, with the keys and gamepad buttons bound to each action
+    pub actions: String,
+    /// Bindings that replace those of the same actions in the action file
+    pub keybindings: BTreeMap<String, Vec<String>>,
+    /// Global volume, from 0 to 1
+    pub volume: f32,
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+impl Default for SvarogConfig {
+    fn default() -> Self {
+        Self {
+            title: "Svarog".to_string(),
+            mode: SvarogWindowMode::Windowed(1024, 768),
+            vsync: true,
+            scale_factor: None,
+            scaling: None,
+            tileset: String::new(),
+            actions: DEFAULT_ACTIONS_PATH.to_string(),
+            keybindings: BTreeMap::new(),
+            volume: 1.0,
+            path: PathBuf::from(DEFAULT_CONFIG_PATH),
+        }
+    }
+}
+
+/// Picks the config file: a `--config <path>` or `--config=<path>` argument, then the `SVAROG_CONFIG` variable,
+/// then `config.ron`
+pub fn config_path_from(mut args: impl Iterator<Item = String>, env: Option<String>) -> PathBuf {
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            if let Some(path) = args.next() {
+                return PathBuf::from(path);
+            }
+        } else if let Some(path) = arg.strip_prefix("--config=") {
+            return PathBuf::from(path);
+        }
+    }
+
+    env.filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH))
+}
+
+pub fn config_path() -> PathBuf {
+    config_path_from(std::env::args().skip(1), std::env::var(CONFIG_ENV).ok())
+}
+
+impl SvarogConfig {
+    /// Reads the config, with defaults for whatever it leaves out. A missing file means all defaults;
+    /// a file that can't be read gives defaults as well, along with the error to report.
+    pub fn load(path: &Path) -> (Self, Option<SvarogError>) {
+        let (config, error) = match fs::read_to_string(path) {
+            Ok(text) => match ron::de::from_str::<SvarogConfig>(&text) {
+                Ok(config) => (config, None),
+                Err(error) => (SvarogConfig::default(), Some(SvarogError::Config { path: path.display().to_string(), message: error.to_string() })),
+            },
+            Err(error) if error.kind() == ErrorKind::NotFound => (SvarogConfig::default(), None),
+            Err(error) => (SvarogConfig::default(), Some(SvarogError::Io { path: path.display().to_string(), error })),
+        };
+
+        (SvarogConfig { path: path.to_path_buf(), ..config }, error)
+    }
+
+    /// Writes the config back to the file it was loaded from
+    pub fn save(&self) -> Result<(), SvarogError> {
+        let text = ron::ser::to_string_pretty(self, PrettyConfig::default())
+            .map_err(|error| SvarogError::Config { path: self.path.display().to_string(), message: error.to_string() })?;
+        fs::write(&self.path, text)
+            .map_err(|error| SvarogError::Io { path: self.path.display().to_string(), error })
+    }
+
+    pub fn present_mode(&self) -> PresentMode {
+        if self.vsync { PresentMode::AutoVsync } else { PresentMode::AutoNoVsync }
+    }
+}
+
+/// Applies changes to the config to the window and the global volume, leaving alone whatever wasn't changed
+pub fn apply_config(
+    config: Res<SvarogConfig>,
+    mut volume: ResMut<GlobalVolume>,
+    mut window: Query<&mut Window, With<PrimaryWindow>>,
+    mut applied: Local<Option<SvarogConfig>>,
+) {
+    if !config.is_changed() {
+        return;
+    }
+
+    let Ok(mut window) = window.get_single_mut() else { return; };
+    let previous = applied.take();
+    let changed = |f: &dyn Fn(&SvarogConfig) -> bool| match previous.as_ref() {
+        Some(previous) => f(previous),
+        None => true,
+    };
+
+    if changed(&|previous| previous.title != config.title) {
+        window.title = config.title.clone();
+    }
+
+    if changed(&|previous| previous.vsync != config.vsync) {
+        window.present_mode = config.present_mode();
+    }
+
+    if changed(&|previous| previous.scale_factor != config.scale_factor) {
+        window.resolution.set_scale_factor_override(config.scale_factor.map(f64::from));
+    }
+
+    if changed(&|previous| previous.mode != config.mode) {
+        match config.mode {
+            SvarogWindowMode::Windowed(w, h) => {
+                window.mode = WindowMode::Windowed;
+                window.resolution.set(w as f32, h as f32);
+            },
+            SvarogWindowMode::Fullscreen => window.mode = WindowMode::BorderlessFullscreen,
+        }
+    }
+
+    if changed(&|previous| previous.volume != config.volume) {
+        *volume = GlobalVolume::new(config.volume.clamp(0.0, 1.0));
+    }
+
+    *applied = Some(config.clone());
+}
+
+#[cfg(test)]
+mod config_testing {
+    use std::path::PathBuf;
+
+    use super::{config_path_from, SvarogConfig, SvarogWindowMode};
+
+    fn args(values: &[&str]) -> impl Iterator<Item = String> {
+        values.iter().map(|value| value.to_string()).collect::<Vec<_>>().into_iter()
+    }
+
+    #[test]
+    fn test_config_path_precedence() {
+        let env = Some("from_env.ron".to_string());
+        assert_eq!(config_path_from(args(&[ "--config", "cli.ron" ]), env.clone()), PathBuf::from("cli.ron"));
+        assert_eq!(config_path_from(args(&[ "--fast", "--config=cli.ron" ]), env.clone()), PathBuf::from("cli.ron"));
+        assert_eq!(config_path_from(args(&[ "--fast" ]), env), PathBuf::from("from_env.ron"));
+        assert_eq!(config_path_from(args(&[]), None), PathBuf::from("config.ron"));
+    }
+
+    #[test]
+    fn test_partial_config_gets_defaults() {
+        let config: SvarogConfig = ron::de::from_str("Config(title: \"Test\", vsync: false)").unwrap();
+        assert_eq!(config.title, "Test");
+        assert!(!config.vsync);
+        assert_eq!(config.mode, SvarogWindowMode::Windowed(1024, 768));
+        assert_eq!(config.volume, 1.0);
+    }
+
+    #[test]
+    fn test_missing_config_is_all_defaults() {
+        let path = std::env::temp_dir().join("svarog_config_testing_missing.ron");
+        let (config, error) = SvarogConfig::load(&path);
+        assert!(error.is_none());
+        assert_eq!(config, SvarogConfig { path, ..SvarogConfig::default() });
+    }
+
+    #[test]
+    fn test_config_round_trip() {
+        let path = std::env::temp_dir().join("svarog_config_testing.ron");
+        let mut config = SvarogConfig { path: path.clone(), ..SvarogConfig::default() };
+        config.volume = 0.25;
+        config.keybindings.insert("wait".to_string(), vec![ "Period".to_string(), "Numpad5".to_string() ]);
+        config.save().unwrap();
+
+        let (loaded, _) = SvarogConfig::load(&path);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(loaded, config);
+    }
+}
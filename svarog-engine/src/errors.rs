@@ -0,0 +1,135 @@
+use std::{collections::HashSet, fmt::Display};
+
+use bevy::{app::App, ecs::system::Resource, log::{error, warn}};
+
+/// A line of a definition file
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Location {
+    pub path: String,
+    pub line: u64,
+}
+
+impl Location {
+    pub fn new(path: &str, line: u64) -> Self {
+        Self { path: path.to_string(), line }
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.path, self.line)
+    }
+}
+
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum SvarogError {
+    /// A file couldn't be read
+    Io { path: String, error: std::io::Error },
+    /// The asset server couldn't load a definition file, or one of the fonts it uses
+    LoadFailed { path: String },
+    /// A row of a definition table doesn't match its columns
+    MalformedRow { at: Location, message: String },
+    /// A grid or a cell uses a tileset that isn't defined
+    UnknownTileset { at: Option<Location>, tileset: String },
+    /// A tileset uses a font that wasn't loaded
+    UnknownFont { tileset: String, font: String },
+    /// A cell holds a glyph the font of its tileset doesn't have
+    MissingGlyph { grid: Option<String>, tileset: String, glyph: String },
+    /// A font defines a glyph it already defined before
+    DuplicateGlyph { at: Location, glyph: String },
+    /// A glyph lies outside of the texture atlas of a tileset that uses its font
+    GlyphOutOfAtlas { at: Location, glyph: String, x: i32, y: i32, tileset: String, columns: i32, rows: i32 },
+    /// The config file is missing or can't be read
+    Config { path: String, message: String },
+    /// An action is bound to something that isn't a known key or gamepad button
+    UnknownBinding { action: String, binding: String },
+    /// A tileset has no texture atlas in the asset collection
+    MissingAtlas { tileset: String },
+    /// Grids can't be placed, as there is no primary window or no camera
+    NoWindow,
+    /// Something was asked of a grid that doesn't exist
+    UnknownGrid { grid: String },
+    /// A cell outside of a grid was written to
+    OutOfGrid { grid: String, x: i32, y: i32 },
+    /// A REXPaint document has fewer layers than the one asked for
+    MissingLayer { layer: usize },
+}
+
+impl SvarogError {
+    /// Mistakes in how the game uses grids at runtime, rather than problems with what was loaded
+    pub fn is_warning(&self) -> bool {
+        matches!(self, SvarogError::UnknownGrid { .. } | SvarogError::OutOfGrid { .. } | SvarogError::MissingLayer { .. })
+    }
+}
+
+impl Display for SvarogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SvarogError::Io { path, error } => write!(f, "Could not read {}: {}", path, error),
+            SvarogError::LoadFailed { path } => write!(f, "Could not load {} or the fonts it uses", path),
+            SvarogError::MalformedRow { at, message } => write!(f, "{}: malformed row: {}", at, message),
+            SvarogError::UnknownTileset { at: Some(at), tileset } => write!(f, "{}: unknown tileset {}", at, tileset),
+            SvarogError::UnknownTileset { at: None, tileset } => write!(f, "Unknown tileset {}", tileset),
+            SvarogError::UnknownFont { tileset, font } => write!(f, "Tileset {} uses font {}, which isn't loaded", tileset, font),
+            SvarogError::MissingGlyph { grid: Some(grid), tileset, glyph } => write!(f, "Grid {} uses glyph {}, which tileset {} doesn't have", grid, glyph, tileset),
+            SvarogError::MissingGlyph { grid: None, tileset, glyph } => write!(f, "Tileset {} has no glyph {}", tileset, glyph),
+            SvarogError::DuplicateGlyph { at, glyph } => write!(f, "{}: glyph {} is defined again", at, glyph),
+            SvarogError::GlyphOutOfAtlas { at, glyph, x, y, tileset, columns, rows } =>
+                write!(f, "{}: glyph {} at {}, {} is outside of tileset {}, which is {} by {}", at, glyph, x, y, tileset, columns, rows),
+            SvarogError::Config { path, message } => write!(f, "Could not read config {}: {}", path, message),
+            SvarogError::UnknownBinding { action, binding } => write!(f, "Action {} is bound to {}, which isn't a key or gamepad button", action, binding),
+            SvarogError::MissingAtlas { tileset } => write!(f, "No texture atlas for tileset {}", tileset),
+            SvarogError::NoWindow => write!(f, "There is no window or camera to place grids in"),
+            SvarogError::UnknownGrid { grid } => write!(f, "No grid {}", grid),
+            SvarogError::OutOfGrid { grid, x, y } => write!(f, "No cell {}, {} in grid {}", x, y, grid),
+            SvarogError::MissingLayer { layer } => write!(f, "No layer {} in REXPaint document", layer),
+        }
+    }
+}
+
+impl std::error::Error for SvarogError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SvarogError::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Everything that was reported in this app so far, each problem once
+#[derive(Resource, Default, Debug)]
+pub struct Reported {
+    pub errors: Vec<SvarogError>,
+    seen: HashSet<String>,
+}
+
+impl Reported {
+    /// Logs an error and keeps it for the validation pass. The same problem is only reported once,
+    /// so systems that run into it every frame don't flood the log.
+    pub fn report(&mut self, error: SvarogError) {
+        if self.seen.insert(error.to_string()) {
+            if error.is_warning() {
+                warn!("{}", error);
+            } else {
+                error!("{}", error);
+            }
+            self.errors.push(error);
+        }
+    }
+
+    pub fn extend(&mut self, errors: impl IntoIterator<Item = SvarogError>) {
+        for error in errors {
+            self.report(error);
+        }
+    }
+}
+
+/// Reports errors found while a plugin is being built, before there are systems to do it
+pub fn report_on(app: &mut App, errors: impl IntoIterator<Item = SvarogError>) {
+    app.world.get_resource_or_insert_with(Reported::default).extend(errors);
+}
+
+/// With strict mode on, startup fails if anything was reported while loading
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StrictMode(pub bool);
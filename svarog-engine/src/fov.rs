@@ -0,0 +1,347 @@
+use std::{collections::HashSet, marker::PhantomData};
+
+use bevy::{app::{Plugin, PostUpdate}, ecs::{component::Component, query::Changed, schedule::{common_conditions::in_state, IntoSystemConfigs},
+    system::{Query, Res, ResMut, Resource}}};
+use doryen_fov::{FovAlgorithm, FovRecursiveShadowCasting, FovRestrictive, MapData};
+
+use crate::{loading::{strings, Fog, Fonts, GridKind, Grids, SvarogStates, Tilesets}, update::grid_stream_chunks};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FovKind {
+    #[default]
+    ShadowCasting,
+    Restrictive,
+}
+
+/// What an entity standing at (x, y) of the FOV source grid can see
+#[derive(Component, Debug)]
+pub struct Viewshed {
+    pub x: i32,
+    pub y: i32,
+    pub radius: i32,
+    pub algorithm: FovKind,
+    pub light_walls: bool,
+    pub visible: HashSet<(i32, i32)>,
+    computed: Option<(i32, i32, i32, FovKind, bool, u64)>,
+}
+
+impl Viewshed {
+    pub fn new(x: i32, y: i32, radius: i32) -> Self {
+        Self {
+            x,
+            y,
+            radius,
+            algorithm: FovKind::default(),
+            light_walls: true,
+            visible: HashSet::new(),
+            computed: None,
+        }
+    }
+
+    pub fn with_algorithm(mut self, algorithm: FovKind) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    pub fn can_see(&self, x: i32, y: i32) -> bool {
+        self.visible.contains(&(x, y))
+    }
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct FovSettings {
+    /// The grid whose cells block sight; for a `Boolean` grid, every set cell does
+    pub source: String,
+    /// Glyphs in the source grid tagged with this attribute block sight
+    pub blocking: String,
+    /// Glyph grids that get dimmed and hidden according to what is seen
+    pub masked: Vec<String>,
+    /// How bright remembered cells are compared to visible ones
+    pub remembered: f32,
+}
+
+/// Transparency of the source grid, along with what all the viewsheds together see and have seen
+#[derive(Resource, Default, Debug)]
+pub struct FovMap {
+    pub width: i32,
+    pub height: i32,
+    pub transparent: Vec<bool>,
+    pub visible: Vec<bool>,
+    pub remembered: Vec<bool>,
+    pub version: u64,
+    source_version: Option<u64>,
+}
+
+impl FovMap {
+    pub fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            None
+        } else {
+            Some((y * self.width + x) as usize)
+        }
+    }
+
+    pub fn is_transparent(&self, x: i32, y: i32) -> bool {
+        self.index(x, y).map(|i| self.transparent[i]).unwrap_or(false)
+    }
+
+    pub fn is_visible(&self, x: i32, y: i32) -> bool {
+        self.index(x, y).map(|i| self.visible[i]).unwrap_or(false)
+    }
+
+    pub fn is_remembered(&self, x: i32, y: i32) -> bool {
+        self.index(x, y).map(|i| self.remembered[i]).unwrap_or(false)
+    }
+}
+
+pub fn fov_rebuild_map(
+    settings: Res<FovSettings>,
+    grids: Res<Grids>,
+    tilesets: Res<Tilesets>,
+    fonts: Res<Fonts>,
+    mut map: ResMut<FovMap>,
+) {
+    let Some(grid) = grids.grids.get(&settings.source) else { return; };
+    if map.source_version == Some(grid.version) {
+        return;
+    }
+
+    if map.width != grid.width || map.height != grid.height {
+        let size = (grid.width * grid.height) as usize;
+        map.width = grid.width;
+        map.height = grid.height;
+        map.visible = vec![ false; size ];
+        map.remembered = vec![ false; size ];
+    }
+
+    let mut strings = strings().lock().unwrap();
+    let mut transparent = vec![ true; (grid.width * grid.height) as usize ];
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            let blocks = if grid.kind == GridKind::Boolean {
+                grid.is_set(x, y)
+            } else {
+                grid.cell(x, y)
+                    .filter(|cell| !cell.is_empty())
+                    .and_then(|cell| strings.out(cell.glyph))
+                    .and_then(|name| fonts.glyph(&tilesets, &grid.tileset, name))
+                    .map(|glyph| glyph.has_tag(&settings.blocking))
+                    .unwrap_or(false)
+            };
+
+            transparent[(y * grid.width + x) as usize] = !blocks;
+        }
+    }
+
+    map.transparent = transparent;
+    map.source_version = Some(grid.version);
+    map.version += 1;
+}
+
+pub fn fov_compute_viewsheds(map: Res<FovMap>, mut viewsheds: Query<&mut Viewshed>) {
+    for mut viewshed in &mut viewsheds {
+        let key = (viewshed.x, viewshed.y, viewshed.radius, viewshed.algorithm, viewshed.light_walls, map.version);
+        if viewshed.computed == Some(key) {
+            continue;
+        }
+
+        viewshed.computed = Some(key);
+        viewshed.visible.clear();
+
+        if map.index(viewshed.x, viewshed.y).is_none() {
+            continue;
+        }
+
+        let mut data = MapData::new(map.width as usize, map.height as usize);
+        for y in 0..map.height {
+            for x in 0..map.width {
+                data.set_transparent(x as usize, y as usize, map.is_transparent(x, y));
+            }
+        }
+
+        let (x, y, radius) = (viewshed.x as usize, viewshed.y as usize, viewshed.radius.max(0) as usize);
+        match viewshed.algorithm {
+            FovKind::ShadowCasting => FovRecursiveShadowCasting::new().compute_fov(&mut data, x, y, radius, viewshed.light_walls),
+            FovKind::Restrictive => FovRestrictive::new().compute_fov(&mut data, x, y, radius, viewshed.light_walls),
+        }
+
+        for j in 0..map.height {
+            for i in 0..map.width {
+                if data.is_in_fov(i as usize, j as usize) {
+                    viewshed.visible.insert((i, j));
+                }
+            }
+        }
+    }
+}
+
+pub fn fov_apply(
+    settings: Res<FovSettings>,
+    mut map: ResMut<FovMap>,
+    changed: Query<(), Changed<Viewshed>>,
+    viewsheds: Query<&Viewshed>,
+    mut grids: ResMut<Grids>,
+) {
+    if changed.is_empty() && !map.is_changed() {
+        return;
+    }
+
+    let map = &mut *map;
+    map.visible.iter_mut().for_each(|v| *v = false);
+    for viewshed in &viewsheds {
+        for &(x, y) in &viewshed.visible {
+            if let Some(index) = map.index(x, y) {
+                map.visible[index] = true;
+                map.remembered[index] = true;
+            }
+        }
+    }
+
+    for name in &settings.masked {
+        let Some(grid) = grids.grids.get_mut(name) else { continue; };
+        // fog is worked out per cell of the source grid, so it can't be laid over a grid of another size
+        if grid.width != map.width || grid.height != map.height {
+            continue;
+        }
+
+        grid.remembered = settings.remembered;
+
+        for y in 0..grid.height {
+            for x in 0..grid.width {
+                let fog = if map.is_visible(x, y) {
+                    Fog::Visible
+                } else if map.is_remembered(x, y) {
+                    Fog::Remembered
+                } else {
+                    Fog::Unseen
+                };
+
+                if let Some(index) = grid.index(x, y) {
+                    grid.set_fog(index, fog);
+                }
+            }
+        }
+    }
+}
+
+/// Computes field of view for every `Viewshed` and fogs the masked grids accordingly
+pub struct SvarogFovPlugin<S: SvarogStates> {
+    settings: FovSettings,
+    _marker: PhantomData<S>,
+}
+
+impl<S: SvarogStates> SvarogFovPlugin<S> {
+    pub fn new(source: &str, blocking: &str) -> Self {
+        Self {
+            settings: FovSettings {
+                source: source.to_string(),
+                blocking: blocking.to_string(),
+                masked: vec![ source.to_string() ],
+                remembered: 0.35,
+            },
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn masking(mut self, grids: &[&str]) -> Self {
+        self.settings.masked = grids.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    pub fn remembered(mut self, brightness: f32) -> Self {
+        self.settings.remembered = brightness;
+        self
+    }
+}
+
+impl<S: SvarogStates> Plugin for SvarogFovPlugin<S> {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.insert_resource(self.settings.clone());
+        app.init_resource::<FovMap>();
+        app.add_systems(PostUpdate, (fov_rebuild_map, fov_compute_viewsheds, fov_apply)
+            .chain()
+            .before(grid_stream_chunks)
+            .run_if(in_state(S::done_loading_state())));
+    }
+}
+
+#[cfg(test)]
+mod fov_testing {
+    use bevy::{app::{App, Update}, ecs::{entity::Entity, schedule::IntoSystemConfigs}};
+
+    use crate::loading::{Fog, Fonts, Grid, GridKind, Grids, Tilesets};
+
+    use super::{fov_apply, fov_compute_viewsheds, fov_rebuild_map, FovMap, FovSettings, Viewshed};
+
+    fn grid(name: &str, width: i32, height: i32, kind: GridKind) -> Grid {
+        let mut grid = Grid { name: name.to_string(), width, height, kind, ..Default::default() };
+        grid.allocate();
+        grid
+    }
+
+    /// A 7x3 room split by a wall on column 3, with a viewer at (1, 1)
+    fn app() -> (App, Entity) {
+        let mut grids = Grids::default();
+        grids.grids.insert("walls".to_string(), grid("walls", 7, 3, GridKind::Boolean));
+        grids.grids.insert("map".to_string(), grid("map", 7, 3, GridKind::Glyph));
+        grids.grids.insert("minimap".to_string(), grid("minimap", 3, 1, GridKind::Glyph));
+        for y in 0..3 {
+            grids.flag("walls", 3, y, true);
+        }
+
+        let mut app = App::new();
+        app.insert_resource(grids).init_resource::<Tilesets>().init_resource::<Fonts>().init_resource::<FovMap>()
+            .insert_resource(FovSettings {
+                source: "walls".to_string(),
+                blocking: "blocks".to_string(),
+                masked: vec![ "map".to_string(), "minimap".to_string() ],
+                remembered: 0.35,
+            })
+            .add_systems(Update, (fov_rebuild_map, fov_compute_viewsheds, fov_apply).chain());
+
+        let viewer = app.world.spawn(Viewshed::new(1, 1, 10)).id();
+        app.update();
+        (app, viewer)
+    }
+
+    fn fog(app: &App, grid: &str, x: i32, y: i32) -> Fog {
+        let grid = &app.world.resource::<Grids>().grids[grid];
+        grid.fog[grid.index(x, y).unwrap()]
+    }
+
+    #[test]
+    fn test_boolean_wall_hides_what_is_behind_it() {
+        let (mut app, viewer) = app();
+        assert!(!app.world.resource::<FovMap>().is_transparent(3, 1));
+
+        let viewshed = app.world.get::<Viewshed>(viewer).unwrap();
+        assert!(viewshed.can_see(2, 1) && viewshed.can_see(3, 1));
+        assert!(!viewshed.can_see(5, 1));
+        assert_eq!(fog(&app, "map", 5, 1), Fog::Unseen);
+
+        app.world.get_mut::<Viewshed>(viewer).unwrap().light_walls = false;
+        app.update();
+        assert!(!app.world.get::<Viewshed>(viewer).unwrap().can_see(3, 1));
+    }
+
+    #[test]
+    fn test_cells_stay_remembered_out_of_view() {
+        let (mut app, viewer) = app();
+        assert_eq!(fog(&app, "map", 0, 1), Fog::Visible);
+
+        let mut viewshed = app.world.get_mut::<Viewshed>(viewer).unwrap();
+        viewshed.x = 5;
+        app.update();
+
+        assert_eq!(fog(&app, "map", 0, 1), Fog::Remembered);
+        assert_eq!(fog(&app, "map", 6, 1), Fog::Visible);
+        assert!(app.world.resource::<FovMap>().is_remembered(0, 1));
+    }
+
+    #[test]
+    fn test_masked_grid_of_another_size_is_skipped() {
+        let (app, _) = app();
+        assert_eq!(fog(&app, "map", 5, 1), Fog::Unseen);
+        assert!((0..3).all(|x| fog(&app, "minimap", x, 0) == Fog::Visible));
+    }
+}
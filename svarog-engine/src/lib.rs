@@ -1,14 +1,25 @@
 use std::marker::PhantomData;
 
 use bevy::app::App;
-use self::{loading::{Fonts, Grids, SvarogLoadingPlugin, SvarogStates, SvarogTextureAtlases, Tilesets}, 
-    update::SvarogGridPlugin, windows::SvarogWindowPlugin};
+use self::{actions::SvarogActionPlugin, errors::StrictMode, loading::{Fonts, Grids, SvarogLoadingPlugin, SvarogStates, SvarogTextureAtlases, Tilesets}, 
+    picking::SvarogPickingPlugin, rex::SvarogRexPlugin, update::SvarogGridPlugin, widgets::SvarogWidgetPlugin, windows::SvarogWindowPlugin};
 
 pub mod windows;
 pub mod loading;
 pub mod tables;
 pub mod rex;
 pub mod update;
+pub mod fov;
+pub mod navigation;
+pub mod camera;
+pub mod text;
+pub mod snapshot;
+pub mod reload;
+pub mod errors;
+pub mod config;
+pub mod picking;
+pub mod actions;
+pub mod widgets;
 
 pub struct Svarog<A: SvarogTextureAtlases, S: SvarogStates>(pub(crate) App, PhantomData<(A, S)>);
 
@@ -17,7 +28,11 @@ impl<A: SvarogTextureAtlases, S: SvarogStates> Default for Svarog<A, S> {
         Self({
             let mut app = App::default();
             app.add_plugins(SvarogWindowPlugin);
+            app.add_plugins(SvarogActionPlugin);
+            app.add_plugins(SvarogRexPlugin);
             app.add_plugins(SvarogGridPlugin::<S>::default());
+            app.add_plugins(SvarogPickingPlugin::<S>::default());
+            app.add_plugins(SvarogWidgetPlugin::<S>::default());
             app
         }, PhantomData)
     }
@@ -29,6 +44,12 @@ impl<A: SvarogTextureAtlases, S: SvarogStates> Svarog<A, S> {
         self
     }
 
+    /// Fails startup if anything goes wrong while loading, instead of reporting it and carrying on
+    pub fn strict(mut self) -> Self {
+        self.0.insert_resource(StrictMode(true));
+        self
+    }
+
     pub fn as_bevy(self) -> App {
         self.0
     }
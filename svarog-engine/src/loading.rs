@@ -1,9 +1,11 @@
-use bevy::{app::Plugin, asset::Handle, core_pipeline::core_2d::Camera2dBundle, ecs::{component::Component, entity::Entity, query::With, schedule::{NextState, OnEnter, States}, 
-    system::{Commands, Query, Res, ResMut, Resource}}, hierarchy::BuildChildren, math::Vec3, render::view::{InheritedVisibility, Visibility}, 
-    sprite::{SpriteSheetBundle, TextureAtlas, TextureAtlasSprite}, transform::components::{GlobalTransform, Transform}, 
-    utils::hashbrown::HashMap, window::{PrimaryWindow, Window}};
+use bevy::{app::{Last, Plugin, Update}, asset::{AssetServer, Assets, Handle, RecursiveDependencyLoadState}, core_pipeline::core_2d::Camera2dBundle, ecs::{component::Component, entity::Entity, event::EventReader, query::With, 
+    schedule::{common_conditions::in_state, IntoSystemConfigs, NextState, OnEnter, States}, system::{Commands, Query, Res, ResMut, Resource}}, hierarchy::{BuildChildren, DespawnRecursiveExt}, math::{Vec2, Vec3}, render::{color::Color, view::{InheritedVisibility, Visibility}}, 
+    sprite::TextureAtlas, transform::components::{GlobalTransform, Transform}, 
+    utils::hashbrown::HashMap, window::{PrimaryWindow, Window, WindowResized}};
 use bevy_asset_loader::{asset_collection::AssetCollection, loading_state::{config::ConfigureLoadingState, LoadingState, LoadingStateAppExt}, standard_dynamic_asset::StandardDynamicAssetCollection};
 use csv::Trim;
+use serde::de::DeserializeOwned;
+use crate::{config::SvarogConfig, errors::{Location, Reported, StrictMode, SvarogError}, reload::{watch_definitions, Definition, DefinitionFile, SvarogReloadPlugin, WatchedDefinitions}, rex::RexpaintDocument, text::{compile, style, CellRect, Styled, TextOptions}, windows::{SvarogScale, SvarogWindowSize}};
 use std::{collections::HashSet, fmt::Debug, hash::{DefaultHasher, Hash, Hasher}, marker::PhantomData, sync::{Mutex, OnceLock}};
 
 //use super::{GameAssets, GameStates};
@@ -19,10 +21,49 @@ pub trait SvarogTextureAtlases : AssetCollection + Default {
     fn get(&self, name: &str) -> Option<Handle<TextureAtlas>>;
 }
 
+/// A reader for the `|` separated tables definitions are written in
+pub fn definitions(text: &str) -> csv::Reader<&[u8]> {
+    csv::ReaderBuilder::new()
+        .delimiter(b'|')
+        .comment(Some(b'#'))
+        .trim(Trim::All)
+        .from_reader(text.as_bytes())
+}
+
+/// The rows of a definition table with the lines they're on. Rows that can't be read are skipped, and returned as errors.
+pub fn definition_rows<T: DeserializeOwned>(path: &str, text: &str) -> (Vec<(u64, T)>, Vec<SvarogError>) {
+    let mut reader = definitions(text);
+    let headers = match reader.headers() {
+        Ok(headers) => headers.clone(),
+        Err(error) => return (vec![], vec![ SvarogError::MalformedRow { at: Location::new(path, 1), message: error.to_string() } ]),
+    };
+
+    let mut rows = vec![];
+    let mut errors = vec![];
+    for record in reader.records() {
+        let row = record.and_then(|record| {
+            let line = record.position().map(|position| position.line()).unwrap_or_default();
+            record.deserialize::<T>(Some(&headers)).map(|row| (line, row))
+        });
+
+        match row {
+            Ok(row) => rows.push(row),
+            Err(error) => {
+                let line = error.position().map(|position| position.line()).unwrap_or_default();
+                errors.push(SvarogError::MalformedRow { at: Location::new(path, line), message: error.to_string() });
+            },
+        }
+    }
+
+    (rows, errors)
+}
+
 #[derive(Default, Debug)]
 pub struct Font {
     pub glyphs: HashMap<String, Glyph>,
     pub attributes: HashMap<String, HashSet<Glyph>>,
+    /// Line of the font file each glyph was defined on
+    pub lines: HashMap<String, u64>,
 }
 
 #[derive(serde::Deserialize, Debug, PartialEq, Eq, Hash)]
@@ -41,21 +82,41 @@ pub struct Glyph {
     pub attributes: Vec<String>,
 }
 
+impl Glyph {
+    /// The tags of a glyph, as written in its attributes and separated by commas, like `wall, brick, cost=2`
+    pub fn tags(&self) -> impl Iterator<Item = &str> + '_ {
+        self.attributes.iter().flat_map(|attribute| attribute.split(',')).map(str::trim).filter(|tag| !tag.is_empty())
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags().any(|t| t == tag)
+    }
+}
+
 #[derive(Resource, Default, Debug)]
 pub struct Fonts {
     pub fonts: HashMap<String, Font>,
 }
 
 impl Fonts {
+    /// Finds a glyph by name in the font used by the given tileset
+    pub fn glyph(&self, tilesets: &Tilesets, tileset: &str, name: &str) -> Option<&Glyph> {
+        tilesets.tilesets.get(tileset)
+            .and_then(|tileset| self.fonts.get(&tileset.font))
+            .and_then(|font| font.glyphs.get(name))
+    }
+
+    /// Asks for a font file to be loaded along with the definitions, besides the fonts tilesets use
     pub fn add(&mut self, path: &str) {
-        let Ok(mut csv) = csv::ReaderBuilder::new()
-            .delimiter(b'|')
-            .comment(Some(b'#'))
-            .trim(Trim::All)
-            .from_path(format!("assets/{}", path).as_str()) else { return; };
+        self.fonts.entry(path.to_string()).or_default();
+    }
 
+    /// Reads a font from the text of its definition file, replacing the font loaded from the same path.
+    /// Returns what was wrong with the file.
+    pub fn load(&mut self, path: &str, text: &str) -> Vec<SvarogError> {
         let mut font = Font::default();
-        for record in csv.deserialize::<PreGlyph>().flatten() {
+        let (rows, mut errors) = definition_rows::<PreGlyph>(path, text);
+        for (line, record) in rows {
             let is_quote = record.attributes.starts_with('\"');
             if is_quote {
                 let attributes = record.attributes.clone();
@@ -76,10 +137,11 @@ impl Fonts {
                     };
 
                     if font.glyphs.contains_key(&name) {
-                        println!("Warning: font overrides previous glyph: {}", name);
+                        errors.push(SvarogError::DuplicateGlyph { at: Location::new(path, line), glyph: name.clone() });
                     }
 
                     font.glyphs.insert(name.clone(), glyph.clone());
+                    font.lines.insert(name.clone(), line);
 
                     if !font.attributes.contains_key(&name) {
                         font.attributes.insert(name.clone(), HashSet::new());    
@@ -90,7 +152,7 @@ impl Fonts {
             } else {
                 let name = record.name.clone();
                 if font.glyphs.contains_key(&name) {
-                    println!("Warning: font overrides previous glyph: {}", name);
+                    errors.push(SvarogError::DuplicateGlyph { at: Location::new(path, line), glyph: name.clone() });
                 }
 
                 if !font.attributes.contains_key(&name) {
@@ -109,24 +171,28 @@ impl Fonts {
                 };
                 
                 font.attributes.get_mut(&name).unwrap().insert(glyph.clone());
+                font.lines.insert(name.clone(), line);
                 font.glyphs.insert(name, glyph);
             }
         }
 
         self.fonts.insert(path.to_string(), font);
+        errors
     }
 }
 
-#[derive(serde::Deserialize, Debug, PartialEq, Eq, Hash)]
+#[derive(serde::Deserialize, Debug, PartialEq, Eq, Hash, Default)]
 #[serde(rename_all = "camelCase")]
 pub enum GridKind {
+    #[default]
     Glyph,
     Entity,
     Boolean,
 }
 
-#[derive(serde::Deserialize, Debug, PartialEq, Eq, Hash)]
+#[derive(serde::Deserialize, Debug, PartialEq, Eq, Hash, Default)]
 pub enum GridAlign {
+    #[default]
     None,
     TopLeft,
     BottomLeft,
@@ -142,8 +208,9 @@ pub enum GridAlign {
 pub type AlignFn = Box<dyn Fn(f32, f32, f32, f32) -> f32>;
 
 impl Grid {
-    pub fn align(&self, tileset: &Tileset, window: &Window) -> Option<Vec3> {
-        let (window_width_in_px, window_height_in_px) = (window.width(), window.height());
+    /// Where a camera-aligned grid goes in a view of the given size, in world units (see `SvarogScale::view_of`)
+    pub fn align(&self, tileset: &Tileset, view: Vec2) -> Option<Vec3> {
+        let (window_width_in_px, window_height_in_px) = (view.x, view.y);
         let grid_width_in_chars = self.width as f32 * tileset.width as f32;
         let grid_height_in_chars = self.height as f32 * tileset.height as f32;
         let grid_offset_x_in_chars = (self.x * tileset.width) as f32;
@@ -198,6 +265,50 @@ impl Grid {
 }
 
 #[derive(serde::Deserialize, Debug, PartialEq, Eq, Hash)]
+pub struct PreGrid {
+    pub name: String,
+    pub width: String,
+    pub height: String,
+    pub depth: i32,
+    pub x: i32,
+    pub y: i32,
+    pub kind: GridKind,
+    pub tileset: String,
+    pub align: GridAlign,
+}
+
+impl From<PreGrid> for Grid {
+    fn from(record: PreGrid) -> Self {
+        // sizes that are neither a number nor fill are reported by `Grids::load`
+        fn extent(value: &str) -> (i32, bool) {
+            if value == "fill" {
+                (0, true)
+            } else {
+                (value.parse::<i32>().unwrap_or_default(), false)
+            }
+        }
+
+        let (width, fill_width) = extent(&record.width);
+        let (height, fill_height) = extent(&record.height);
+
+        Grid {
+            name: record.name,
+            width,
+            height,
+            depth: record.depth,
+            x: record.x,
+            y: record.y,
+            kind: record.kind,
+            tileset: record.tileset,
+            align: record.align,
+            fill_width,
+            fill_height,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Default)]
 pub struct Grid {
     pub name: String,
     pub width: i32,
@@ -208,22 +319,298 @@ pub struct Grid {
     pub kind: GridKind,
     pub tileset: String,
     pub align: GridAlign,
-    #[serde(skip_deserializing)]
-    pub entities: Vec<Entity>,
-    #[serde(skip_deserializing)]
+    /// Set when the grid was declared with a `fill` width, and grows and shrinks with the window
+    pub fill_width: bool,
+    /// Set when the grid was declared with a `fill` height
+    pub fill_height: bool,
+    /// Where in the grid definitions this grid comes from
+    pub defined_at: Location,
     pub entity: Option<Entity>,
+    pub atlas: Option<Handle<TextureAtlas>>,
+    /// Chunks of sprites currently spawned for this grid, keyed by chunk coordinates
+    pub chunks: HashMap<(i32, i32), GridChunk>,
+    pub cells: Vec<Cell>,
+    pub dirty: HashSet<usize>,
+    /// Cells with `blink` set, redrawn whenever the blink phase changes
+    pub blinking: HashSet<usize>,
+    pub fog: Vec<Fog>,
+    pub remembered: f32,
+    pub version: u64,
+    pub bits: Vec<u64>,
+    pub occupants: HashMap<usize, HashSet<Entity>>,
+    pub positions: HashMap<Entity, (i32, i32)>,
+}
+
+/// How much of a cell the player knows about; cells are `Visible` unless something like FOV says otherwise
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Fog {
+    #[default]
+    Visible,
+    /// Seen before but not now, drawn dimmed by `Grid::remembered`
+    Remembered,
+    Unseen,
+}
+
+/// What a single grid cell holds, kept in `Grid::cells` independently of the sprites that draw it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cell {
+    pub tileset: u64,
+    pub glyph: u64,
+    pub foreground: Color,
+    pub background: Option<Color>,
+    /// Blinking glyphs are hidden every other `BLINK_RATE` seconds
+    pub blink: bool,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self { 
+            tileset: 0, 
+            glyph: 0, 
+            foreground: Color::WHITE, 
+            background: None,
+            blink: false,
+        }
+    }
+}
+
+impl Cell {
+    pub fn is_empty(&self) -> bool {
+        self.glyph == 0
+    }
+
+    pub fn glyph_name(&self) -> Option<String> {
+        if self.is_empty() {
+            None
+        } else {
+            strings().lock().unwrap().out(self.glyph).cloned()
+        }
+    }
+}
+
+/// Width and height, in cells, of the chunks glyph grids are drawn in
+pub const CHUNK_SIZE: i32 = 16;
+
+/// Sprites drawing a `CHUNK_SIZE` square of a glyph grid, only spawned while the chunk can be seen
+#[derive(Debug, PartialEq)]
+pub struct GridChunk {
+    pub entity: Entity,
+    pub sprites: Vec<Entity>,
+    pub backgrounds: Vec<Entity>,
 }
 
 impl Grid {
-    pub fn get(&self, x: i32, y: i32) -> Option<&Entity> {
-        self.entities.get((y * self.width + (x + 1)) as usize)
+    /// Index into `cells` for a cell, with `y` growing downwards from the top row
+    pub fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            None
+        } else {
+            Some(((self.height - 1 - y) * self.width + x) as usize)
+        }
+    }
+
+    /// Inverse of `index`
+    pub fn coords(&self, index: usize) -> (i32, i32) {
+        let index = index as i32;
+        (index % self.width, self.height - 1 - index / self.width)
+    }
+
+    pub fn chunk_of(&self, x: i32, y: i32) -> (i32, i32) {
+        (x.div_euclid(CHUNK_SIZE), y.div_euclid(CHUNK_SIZE))
+    }
+
+    /// The glyph sprite and background sprite drawing a cell, if its chunk is spawned
+    pub fn sprite(&self, x: i32, y: i32) -> Option<(Entity, Entity)> {
+        let chunk = self.chunks.get(&self.chunk_of(x, y))?;
+        let local = (y.rem_euclid(CHUNK_SIZE) * CHUNK_SIZE + x.rem_euclid(CHUNK_SIZE)) as usize;
+        Some((chunk.sprites[local], chunk.backgrounds[local]))
+    }
+
+    pub fn cell(&self, x: i32, y: i32) -> Option<&Cell> {
+        self.index(x, y).and_then(|index| self.cells.get(index))
+    }
+
+    /// Writes a cell, marking it dirty only if something actually changed
+    pub fn put(&mut self, x: i32, y: i32, cell: Cell) -> bool {
+        let Some(index) = self.index(x, y) else { return false; };
+        let Some(current) = self.cells.get_mut(index) else { return false; };
+
+        if *current != cell {
+            *current = cell;
+            if cell.blink {
+                self.blinking.insert(index);
+            } else {
+                self.blinking.remove(&index);
+            }
+            self.dirty.insert(index);
+            self.version += 1;
+        }
+
+        true
+    }
+
+    pub fn set_fog(&mut self, index: usize, fog: Fog) {
+        let Some(current) = self.fog.get_mut(index) else { return; };
+
+        if *current != fog {
+            *current = fog;
+            self.dirty.insert(index);
+        }
+    }
+
+    pub fn allocate(&mut self) {
+        let size = (self.width * self.height).max(0) as usize;
+        match self.kind {
+            GridKind::Glyph => {
+                let tileset = strings().lock().unwrap().pass(&self.tileset);
+                self.cells = vec![ Cell { tileset, ..Default::default() }; size ];
+                self.fog = vec![ Fog::Visible; size ];
+                self.remembered = 0.35;
+                self.dirty = (0..size).collect();
+                self.blinking.clear();
+            },
+            GridKind::Boolean => {
+                self.bits = vec![ 0; size.div_ceil(64) ];
+            },
+            GridKind::Entity => {
+                self.occupants.clear();
+                self.positions.clear();
+            },
+        }
+    }
+
+    /// The size a `fill` grid should have to reach the edges of the view from its offset
+    pub fn fill_size(&self, tileset: &Tileset, view: Vec2) -> (i32, i32) {
+        let width = if self.fill_width { (view.x / tileset.width as f32) as i32 - self.x } else { self.width };
+        let height = if self.fill_height { (view.y / tileset.height as f32) as i32 - self.y } else { self.height };
+        (width.max(0), height.max(0))
+    }
+
+    /// Changes the size of the grid, keeping whatever was in the cells that are still inside of it
+    pub fn resize(&mut self, width: i32, height: i32) {
+        let cells = std::mem::take(&mut self.cells);
+        let fog = std::mem::take(&mut self.fog);
+        let bits = std::mem::take(&mut self.bits);
+        let positions = std::mem::take(&mut self.positions);
+        let (old_width, old_height) = (self.width, self.height);
+
+        self.width = width;
+        self.height = height;
+        self.allocate();
+
+        let old_index = |x: i32, y: i32| ((old_height - 1 - y) * old_width + x) as usize;
+        for y in 0..height.min(old_height) {
+            for x in 0..width.min(old_width) {
+                let (from, to) = (old_index(x, y), self.index(x, y).unwrap());
+                match self.kind {
+                    GridKind::Glyph => {
+                        self.cells[to] = cells[from];
+                        self.fog[to] = fog[from];
+                        if cells[from].blink {
+                            self.blinking.insert(to);
+                        }
+                    },
+                    GridKind::Boolean => {
+                        if bits[from / 64] & (1 << (from % 64)) != 0 {
+                            self.bits[to / 64] |= 1 << (to % 64);
+                        }
+                    },
+                    GridKind::Entity => {},
+                }
+            }
+        }
+
+        for (entity, (x, y)) in positions {
+            self.place(entity, x, y);
+        }
+
+        self.version += 1;
+    }
+
+    /// Reads a cell of a `Boolean` grid; cells outside of the grid are never set
+    pub fn is_set(&self, x: i32, y: i32) -> bool {
+        self.index(x, y)
+            .and_then(|index| self.bits.get(index / 64).map(|word| word & (1 << (index % 64)) != 0))
+            .unwrap_or(false)
+    }
+
+    pub fn flag(&mut self, x: i32, y: i32, value: bool) {
+        let Some(index) = self.index(x, y) else { return; };
+        let Some(word) = self.bits.get_mut(index / 64) else { return; };
+        let before = *word;
+
+        if value {
+            *word |= 1 << (index % 64);
+        } else {
+            *word &= !(1 << (index % 64));
+        }
+
+        if *word != before {
+            self.version += 1;
+        }
+    }
+
+    pub fn clear_flags(&mut self) {
+        self.bits.iter_mut().for_each(|word| *word = 0);
+        self.version += 1;
+    }
+
+    /// Places an entity into a cell of an `Entity` grid, taking it out of wherever it was before
+    pub fn place(&mut self, entity: Entity, x: i32, y: i32) -> bool {
+        let Some(index) = self.index(x, y) else { return false; };
+        self.remove(entity);
+        self.occupants.entry(index).or_default().insert(entity);
+        self.positions.insert(entity, (x, y));
+        self.version += 1;
+        true
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<(i32, i32)> {
+        let (x, y) = self.positions.remove(&entity)?;
+        if let Some(index) = self.index(x, y) {
+            if let Some(occupants) = self.occupants.get_mut(&index) {
+                occupants.remove(&entity);
+                if occupants.is_empty() {
+                    self.occupants.remove(&index);
+                }
+            }
+        }
+        self.version += 1;
+        Some((x, y))
+    }
+
+    pub fn position(&self, entity: Entity) -> Option<(i32, i32)> {
+        self.positions.get(&entity).copied()
+    }
+
+    pub fn at(&self, x: i32, y: i32) -> impl Iterator<Item = Entity> + '_ {
+        self.index(x, y)
+            .and_then(|index| self.occupants.get(&index))
+            .into_iter()
+            .flat_map(|occupants| occupants.iter().copied())
+    }
+
+    /// All entities within a `w` by `h` rectangle, along with the cell each is in
+    pub fn in_rect(&self, x: i32, y: i32, w: i32, h: i32) -> Vec<(i32, i32, Entity)> {
+        self.positions.iter()
+            .filter(|&(_, &(px, py))| px >= x && py >= y && px < x + w && py < y + h)
+            .map(|(&entity, &(px, py))| (px, py, entity))
+            .collect()
     }
 }
 
 #[derive(Resource, Default, Debug)]
 pub struct Grids {
     pub grids: HashMap<String, Grid>,
+    /// Names of the grids each definition file declared
+    pub sources: HashMap<String, Vec<String>>,
     pub inputs: HashMap<u64, Vec<Word>>,
+    /// Whether blinking cells are in their hidden phase
+    pub blinked: bool,
+    /// Tileset of grids whose definition leaves it empty or says `default`
+    pub default_tileset: String,
+    /// Problems the grid methods ran into, until `report_grid_errors` hands them over to `Reported`
+    pub errors: Vec<SvarogError>,
 }
 
 pub fn strings() -> &'static Mutex<Strings> {
@@ -256,160 +643,264 @@ impl Strings {
     }
 }
 
-#[derive(Component)]
-pub struct SetGridValue {
-    pub tileset: u64,
-    pub value: u64,
-}
-
-#[derive(Debug)]
-enum Token {
-    Token(Vec<char>),
-    Var(Vec<char>),
-}
-
-#[derive(Debug, Clone, Copy)]
+/// A compiled piece of print markup, see `text::compile`
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Word {
     Text(u64),
+    /// A named glyph from the grid's own tileset
     Var(u64),
+    /// A named glyph from another tileset, as (tileset, glyph)
+    VarIn(u64, u64),
+    Foreground(Color),
+    Background(Color),
+    Blink,
+    /// Closes the innermost open span
+    Pop,
 }
 
 impl Grids {
+    /// Asks for a grid definition file to be loaded through the asset server during static loading
     pub fn add(&mut self, path: &str) {
-        let Ok(mut csv) = csv::ReaderBuilder::new()
-            .delimiter(b'|')
-            .comment(Some(b'#'))
-            .trim(Trim::All)
-            .from_path(format!("assets/{}", path).as_str()) else { return; };
+        self.sources.entry(path.to_string()).or_default();
+    }
+
+    /// Reads grids from the text of a definition file. Grids that were already loaded keep their contents,
+    /// unless they changed kind, and grids the file doesn't define anymore are dropped. Returns the entities
+    /// of the grids that changed or were dropped, which have to be despawned and the grids spawned again,
+    /// along with what was wrong with the file.
+    pub fn load(&mut self, path: &str, text: &str) -> (Vec<Entity>, Vec<SvarogError>) {
+        let mut stale = vec![];
+        let mut names = vec![];
+
+        let (rows, mut errors) = definition_rows::<PreGrid>(path, text);
+        for (line, record) in rows {
+            for value in [ &record.width, &record.height ].into_iter().filter(|value| *value != "fill" && value.parse::<i32>().is_err()) {
+                errors.push(SvarogError::MalformedRow {
+                    at: Location::new(path, line),
+                    message: format!("grid {} has a size that is neither a number nor fill: {}", record.name, value),
+                });
+            }
+
+            let mut grid = Grid::from(record);
+            grid.defined_at = Location::new(path, line);
+            if grid.tileset.is_empty() || grid.tileset == "default" {
+                grid.tileset = self.default_tileset.clone();
+            }
+            names.push(grid.name.clone());
+
+            match self.grids.remove(&grid.name) {
+                Some(mut old) if old.kind == grid.kind => {
+                    stale.extend(old.entity.take());
 
-        for record in csv.deserialize::<Grid>().flatten() {
-            self.grids.insert(record.name.clone(), record);
+                    if old.kind == GridKind::Glyph && old.tileset != grid.tileset {
+                        let mut strings = strings().lock().unwrap();
+                        let (from, to) = (strings.pass(&old.tileset), strings.pass(&grid.tileset));
+                        for cell in old.cells.iter_mut().filter(|cell| cell.tileset == from) {
+                            cell.tileset = to;
+                        }
+                    }
+
+                    // fill sizes follow the window, and get worked out when the grid is spawned
+                    let width = if grid.fill_width { old.width } else { grid.width };
+                    let height = if grid.fill_height { old.height } else { grid.height };
+                    if (width, height) != (old.width, old.height) {
+                        old.resize(width, height);
+                    }
+
+                    old.depth = grid.depth;
+                    old.x = grid.x;
+                    old.y = grid.y;
+                    old.tileset = grid.tileset;
+                    old.align = grid.align;
+                    old.fill_width = grid.fill_width;
+                    old.fill_height = grid.fill_height;
+                    old.defined_at = grid.defined_at;
+                    old.version += 1;
+                    grid = old;
+                },
+                Some(mut old) => {
+                    stale.extend(old.entity.take());
+                    grid.allocate();
+                },
+                None => grid.allocate(),
+            }
+
+            self.grids.insert(grid.name.clone(), grid);
         }
+
+        let previous = self.sources.insert(path.to_string(), names.clone()).unwrap_or_default();
+        for name in previous.iter().filter(|name| !names.contains(name)) {
+            if let Some(mut grid) = self.grids.remove(name) {
+                stale.extend(grid.entity.take());
+            }
+        }
+
+        (stale, errors)
     }
 
-    pub fn set(&mut self, commands: &mut Commands, grid: &str, x: i32, y: i32, value: &str) {
-        if let Some(grid) = self.grids.get(grid) {
-            if let Some(tile_entity) = grid.get(x - 1, grid.height - 1 - y) {
-                let mut strings = strings().lock().unwrap();
-                if value.len() > 0 {
-                    commands.entity(*tile_entity).insert(SetGridValue { tileset: strings.pass(&grid.tileset), value: strings.pass(value) });
-                } else {
-                    commands.entity(*tile_entity).insert(SetGridValue { tileset: strings.pass(&grid.tileset), value: 0 });
-                }
-            } else {
-                println!("No grid at x, y: {} {}", x, grid.height - 1 - y);
+    pub fn get(&self, grid: &str, x: i32, y: i32) -> Option<&Cell> {
+        self.grids.get(grid).and_then(|grid| grid.cell(x, y))
+    }
+
+    pub fn is_set(&self, grid: &str, x: i32, y: i32) -> bool {
+        self.grids.get(grid).map(|grid| grid.is_set(x, y)).unwrap_or(false)
+    }
+
+    pub fn flag(&mut self, grid: &str, x: i32, y: i32, value: bool) {
+        if let Some(found) = self.grids.get_mut(grid) {
+            found.flag(x, y, value);
+        } else {
+            self.errors.push(SvarogError::UnknownGrid { grid: grid.to_string() });
+        }
+    }
+
+    pub fn place(&mut self, grid: &str, entity: Entity, x: i32, y: i32) {
+        if let Some(found) = self.grids.get_mut(grid) {
+            if !found.place(entity, x, y) {
+                self.errors.push(SvarogError::OutOfGrid { grid: grid.to_string(), x, y });
             }
         } else {
-            println!("No grid {}", grid);
+            self.errors.push(SvarogError::UnknownGrid { grid: grid.to_string() });
         }
     }
 
-    pub fn print(&mut self, commands: &mut Commands, grid: &str, x: i32, y: i32, value: &str) {
-        let input = { let mut strings = strings().lock().unwrap(); strings.pass(value) };
-        let results = self.inputs.get(&input).cloned().unwrap_or(
-        {
-            let (token, mut results) = value.chars().fold(
-                (Token::Token(vec![]), vec![]), 
-            |(token, mut parts), next_char| {
-                match (token, next_char) {
-                    (Token::Token(token), '/') if token.len() == 0 => {
-                        (Token::Var(vec![]), parts)
-                    },
-                    (Token::Token(token), '/') => {
-                        let mut strings = strings().lock().unwrap();
-                        parts.extend(vec![ Word::Text(strings.pass(&token.iter().collect::<String>())) ]);
-                        (Token::Var(vec![]), parts)
-                    },
-                    (Token::Token(mut token), next_char) => {
-                        token.extend(vec![ next_char ]);
-                        (Token::Token(token), parts)
-                    },
-                    (Token::Var(token), '/') if token.len() == 0 => {
-                        let mut strings = strings().lock().unwrap();
-                        parts.extend(vec![ Word::Text(strings.pass("/")) ]);
-                        (Token::Token(vec![]), parts)
-                    },
-                    (Token::Var(token), '/') => {
-                        let mut strings = strings().lock().unwrap();
-                        parts.extend(vec![ Word::Var(strings.pass(&token.iter().collect::<String>())) ]);
-                        (Token::Token(vec![]), parts)
-                    },
-                    (Token::Var(mut token), next_char) => {
-                        token.extend(vec![ next_char ]);
-                        (Token::Var(token), parts)
-                    },
-                }
-            });
+    pub fn unplace(&mut self, grid: &str, entity: Entity) -> Option<(i32, i32)> {
+        self.grids.get_mut(grid).and_then(|grid| grid.remove(entity))
+    }
 
-            match token {
-                Token::Token(token) | Token::Var(token) if token.is_empty() => {},
-                Token::Token(token) => { 
-                    let mut strings = strings().lock().unwrap();
-                    results.extend(vec![ Word::Text(strings.pass(&token.iter().collect::<String>())) ]); 
-                },
-                Token::Var(token) => { 
-                    let mut strings = strings().lock().unwrap();
-                    results.extend(vec![ Word::Var(strings.pass(&token.iter().collect::<String>())) ]); 
-                },
-            }
+    pub fn entities_at(&self, grid: &str, x: i32, y: i32) -> Vec<Entity> {
+        self.grids.get(grid).map(|grid| grid.at(x, y).collect()).unwrap_or_default()
+    }
 
-            results
+    pub fn entities_in(&self, grid: &str, x: i32, y: i32, w: i32, h: i32) -> Vec<(i32, i32, Entity)> {
+        self.grids.get(grid).map(|grid| grid.in_rect(x, y, w, h)).unwrap_or_default()
+    }
+
+    /// Walks the cells of a `w` by `h` region row by row, skipping any that fall outside of the grid
+    pub fn region(&self, grid: &str, x: i32, y: i32, w: i32, h: i32) -> impl Iterator<Item = (i32, i32, &Cell)> + '_ {
+        let grid = self.grids.get(grid);
+        (y..y + h)
+            .flat_map(move |j| (x..x + w).map(move |i| (i, j)))
+            .filter_map(move |(i, j)| grid.and_then(|grid| grid.cell(i, j)).map(|cell| (i, j, cell)))
+    }
+
+    pub fn set(&mut self, grid: &str, x: i32, y: i32, value: &str) {
+        self.set_colored(grid, x, y, value, Color::WHITE, None);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn set_colored(&mut self, grid: &str, x: i32, y: i32, value: &str, foreground: Color, background: Option<Color>) {
+        let (glyph, tileset) = {
+            let mut strings = strings().lock().unwrap();
+            let tileset = self.grids.get(grid).map(|g| strings.pass(&g.tileset));
+            (if !value.is_empty() { strings.pass(value) } else { 0 }, tileset)
+        };
+
+        self.update(grid, x, y, |cell| {
+            cell.glyph = glyph;
+            cell.tileset = tileset.unwrap_or(cell.tileset);
+            cell.foreground = foreground;
+            cell.background = background;
+            cell.blink = false;
         });
+    }
 
-        let mut index = 0;
-        for ch in results.iter() {
-            match ch {
-                Word::Text(text) => {
-                    let str = { 
-                        let mut strings = strings().lock().unwrap();
-                        strings.out(*text).unwrap().clone()
-                    };
+    /// Sets a glyph along with its colors, blinking and, if it has one, its own tileset
+    pub fn set_styled(&mut self, grid: &str, x: i32, y: i32, styled: &Styled) {
+        let (glyph, tileset) = {
+            let mut strings = strings().lock().unwrap();
+            let tileset = styled.tileset.as_deref().or(self.grids.get(grid).map(|g| g.tileset.as_str())).map(|t| strings.pass(t));
+            (if !styled.glyph.is_empty() { strings.pass(&styled.glyph) } else { 0 }, tileset)
+        };
 
-                    for c in 0..str.len() {
-                        self.set(commands, grid, x + index as i32, y, &str[c..c+1]);
-                        index += 1;
-                    }
-                },
+        self.update(grid, x, y, |cell| {
+            cell.glyph = glyph;
+            cell.tileset = tileset.unwrap_or(cell.tileset);
+            cell.foreground = styled.foreground;
+            cell.background = styled.background;
+            cell.blink = styled.blink;
+        });
+    }
 
-                Word::Var(text) => {
-                    let text = { 
-                        let mut strings = strings().lock().unwrap();
-                        strings.out(*text).unwrap().clone()
-                    };
-                    self.set(commands, grid, x + index as i32, y, &text);
-                    index += 1;
-                }
+    pub fn set_foreground(&mut self, grid: &str, x: i32, y: i32, foreground: Color) {
+        self.update(grid, x, y, |cell| cell.foreground = foreground);
+    }
+
+    pub fn set_background(&mut self, grid: &str, x: i32, y: i32, background: Option<Color>) {
+        self.update(grid, x, y, |cell| cell.background = background);
+    }
+
+    fn update<F: FnOnce(&mut Cell)>(&mut self, grid: &str, x: i32, y: i32, f: F) {
+        if let Some(found) = self.grids.get_mut(grid) {
+            if let Some(mut cell) = found.cell(x, y).copied() {
+                f(&mut cell);
+                found.put(x, y, cell);
+            } else {
+                self.errors.push(SvarogError::OutOfGrid { grid: grid.to_string(), x, y });
             }
+        } else {
+            self.errors.push(SvarogError::UnknownGrid { grid: grid.to_string() });
         }
     }
 
-    pub fn rect(&mut self, commands: &mut Commands, grid: &str, x: i32, y: i32, w: i32, h: i32, value: &str) {
+    pub fn print(&mut self, grid: &str, x: i32, y: i32, value: &str) {
+        self.print_colored(grid, x, y, value, Color::WHITE, None);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn print_colored(&mut self, grid: &str, x: i32, y: i32, value: &str, foreground: Color, background: Option<Color>) {
+        let styled = style(&self.words(value), foreground, background);
+        for (index, glyph) in styled.iter().enumerate() {
+            let x = x + index as i32;
+            if self.contains(grid, x, y) {
+                self.set_styled(grid, x, y, glyph);
+            }
+        }
+    }
+
+    /// Compiles print markup, reusing the result for text that was printed before
+    pub fn words(&mut self, value: &str) -> Vec<Word> {
+        let input = { let mut strings = strings().lock().unwrap(); strings.pass(value) };
+        self.inputs.entry(input).or_insert_with(|| compile(value)).clone()
+    }
+
+    /// Whether (x, y) lies inside the grid, for writing that should clip rather than complain
+    pub fn contains(&self, grid: &str, x: i32, y: i32) -> bool {
+        self.grids.get(grid).and_then(|grid| grid.index(x, y)).is_some()
+    }
+
+    pub fn rect(&mut self, grid: &str, x: i32, y: i32, w: i32, h: i32, value: &str) {
+        self.rect_colored(grid, x, y, w, h, value, Color::WHITE, None);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn rect_colored(&mut self, grid: &str, x: i32, y: i32, w: i32, h: i32, value: &str, foreground: Color, background: Option<Color>) {
         for dx in x..=(x + w) {
             for dy in y..=(y + h) {
-                self.set(commands, grid, dx, dy, value);
+                self.set_colored(grid, dx, dy, value, foreground, background);
             }
         }
     }
 
     //                        0   1   2   3  4  5  6  7  8
     /// Slices go like this: TL, TR, BL, BR, T, B, L, R, M
-    pub fn boxed(&mut self, commands: &mut Commands, grid: &str, x: i32, y: i32, w: i32, h: i32, slices: &[&str; 9]) {
-        self.rect(commands, grid, x, y, w, h, slices[8]);
+    pub fn boxed(&mut self, grid: &str, x: i32, y: i32, w: i32, h: i32, slices: &[&str; 9]) {
+        self.rect(grid, x, y, w, h, slices[8]);
 
         for i in x..=x+w {
-            self.set(commands, grid, i, y, slices[4]);
-            self.set(commands, grid, i, y+h, slices[5]);
+            self.set(grid, i, y, slices[4]);
+            self.set(grid, i, y+h, slices[5]);
         }
 
         for j in y..=y+h {
-            self.set(commands, grid, x, j, slices[6]);
-            self.set(commands, grid, x+w, j, slices[7]);
+            self.set(grid, x, j, slices[6]);
+            self.set(grid, x+w, j, slices[7]);
         }
 
-        self.set(commands, grid, x, y, slices[0]);
-        self.set(commands, grid, x+w, y, slices[1]);
-        self.set(commands, grid, x, y+h, slices[2]);
-        self.set(commands, grid, x+w, y+h, slices[3]);
+        self.set(grid, x, y, slices[0]);
+        self.set(grid, x+w, y, slices[1]);
+        self.set(grid, x, y+h, slices[2]);
+        self.set(grid, x+w, y+h, slices[3]);
     }
 }
 
@@ -427,25 +918,63 @@ impl<'a, 'w, 's> GridEditor<'a, 'w, 's> {
     }
 
     pub fn set(&mut self, grid: &str, x: i32, y: i32, value: &str) {
-        self.grids.set(self.commands, grid, x, y, value);
+        self.grids.set(grid, x, y, value);
+    }
+
+    pub fn get(&self, grid: &str, x: i32, y: i32) -> Option<&Cell> {
+        self.grids.get(grid, x, y)
+    }
+
+    /// Sets a glyph tinted with `foreground`, drawn over a solid `background` if there is one
+    pub fn set_colored(&mut self, grid: &str, x: i32, y: i32, value: &str, foreground: Color, background: Option<Color>) {
+        self.grids.set_colored(grid, x, y, value, foreground, background);
+    }
+
+    /// Changes only the tint of a cell, keeping its glyph and background
+    pub fn tint(&mut self, grid: &str, x: i32, y: i32, foreground: Color) {
+        self.grids.set_foreground(grid, x, y, foreground);
+    }
+
+    /// Changes only the background of a cell, keeping its glyph and tint
+    pub fn shade(&mut self, grid: &str, x: i32, y: i32, background: Option<Color>) {
+        self.grids.set_background(grid, x, y, background);
     }
 
     pub fn print(&mut self, grid: &str, x: i32, y: i32, value: &str) {
-        self.grids.print(self.commands, grid, x, y, value);
+        self.grids.print(grid, x, y, value);
+    }
+
+    pub fn print_colored(&mut self, grid: &str, x: i32, y: i32, value: &str, foreground: Color, background: Option<Color>) {
+        self.grids.print_colored(grid, x, y, value, foreground, background);
+    }
+
+    /// Prints wrapped and aligned text inside a rectangle, returning how many lines it took
+    pub fn print_box(&mut self, grid: &str, rect: CellRect, text: &str, options: &TextOptions) -> i32 {
+        self.grids.print_box(grid, rect, text, options)
     }
 
     pub fn rect(&mut self, grid: &str, x: i32, y: i32, w: i32, h: i32, value: &str) {
-        self.grids.rect(self.commands, grid, x, y, w - 1, h - 1, value);
+        self.grids.rect(grid, x, y, w - 1, h - 1, value);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn rect_colored(&mut self, grid: &str, x: i32, y: i32, w: i32, h: i32, value: &str, foreground: Color, background: Option<Color>) {
+        self.grids.rect_colored(grid, x, y, w - 1, h - 1, value, foreground, background);
     }
 
     /// Slices go like this: TL, TR, BL, BR, T, B, L, R, M
     pub fn custom_frame(&mut self, grid: &str, x: i32, y: i32, w: i32, h: i32, slices: &[&str; 9]) {
-        self.grids.boxed(self.commands, grid, x, y, w - 1, h - 1, slices);
+        self.grids.boxed(grid, x, y, w - 1, h - 1, slices);
     }
 
     pub fn frame(&mut self, grid: &str, x: i32, y: i32, w: i32, h: i32) {
         self.custom_frame(grid, x, y, w, h, &[ "topleft", "topright", "bottomleft", "bottomright", "top", "bottom", "left", "right", " " ]);
     }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn blit_xp(&mut self, tilesets: &Tilesets, fonts: &Fonts, grid: &str, x: i32, y: i32, doc: &RexpaintDocument, layer: usize) {
+        self.grids.blit_xp(tilesets, fonts, grid, x, y, doc, layer);
+    }
 }
 
 #[derive(serde::Deserialize, Debug, PartialEq, Eq, Hash)]
@@ -462,24 +991,85 @@ pub struct Tileset {
 #[derive(Resource, Default, Debug)]
 pub struct Tilesets {
     pub tilesets: HashMap<String, Tileset>,
+    /// Names of the tilesets each definition file declared
+    pub sources: HashMap<String, Vec<String>>,
+    /// Texture atlas of each tileset, for drawing cells that take their glyph from another tileset than their grid's
+    pub atlases: HashMap<String, Handle<TextureAtlas>>,
 }
 
 impl Tilesets {
-    pub fn add(&mut self, path: &str, fonts: &mut Fonts) {
-        let Ok(mut csv) = csv::ReaderBuilder::new()
-            .delimiter(b'|')
-            .comment(Some(b'#'))
-            .trim(Trim::All)
-            .from_path(format!("assets/{}", path).as_str()) else { return; };
-
-        for record in csv.deserialize::<Tileset>().flatten() {
-            fonts.add(&record.font);
+    /// Picks up the texture atlas of every tileset from the asset collection
+    pub fn attach_atlases<GameAssets: SvarogTextureAtlases>(&mut self, assets: &GameAssets) {
+        self.atlases = self.tilesets.keys().filter_map(|name| assets.get(name).map(|atlas| (name.clone(), atlas))).collect();
+    }
+
+    /// Asks for a tilesets definition file to be loaded through the asset server during static loading;
+    /// the fonts its tilesets use get loaded along with it
+    pub fn add(&mut self, path: &str) {
+        self.sources.entry(path.to_string()).or_default();
+    }
+
+    /// Reads tilesets from the text of a definition file, returning what was wrong with it. Tilesets the file
+    /// doesn't define anymore are dropped. Their fonts are loaded as dependencies of the file, see `DefinitionFile::fonts`.
+    pub fn load(&mut self, path: &str, text: &str) -> Vec<SvarogError> {
+        let mut names = vec![];
+        let (rows, errors) = definition_rows::<Tileset>(path, text);
+        for (_, record) in rows {
+            names.push(record.name.clone());
             self.tilesets.insert(record.name.clone(), record);
         }
+
+        let previous = self.sources.insert(path.to_string(), names.clone()).unwrap_or_default();
+        for name in previous.iter().filter(|name| !names.contains(name)) {
+            self.tilesets.remove(name);
+            self.atlases.remove(name);
+        }
+
+        errors
     }
 }
 
-pub fn start_static_loading<GameStates: SvarogStates>(mut next: ResMut<NextState<GameStates>>) {
+/// Reads the definition files once the asset server has loaded them and their fonts, then moves on to loading assets
+#[allow(clippy::too_many_arguments)]
+pub fn finish_static_loading<GameStates: SvarogStates>(
+    asset_server: Res<AssetServer>,
+    files: Res<Assets<DefinitionFile>>,
+    watched: Res<WatchedDefinitions>,
+    mut tilesets: ResMut<Tilesets>,
+    mut fonts: ResMut<Fonts>,
+    mut grids: ResMut<Grids>,
+    mut reported: ResMut<Reported>,
+    mut next: ResMut<NextState<GameStates>>,
+) {
+    let mut loaded = watched.definitions().collect::<Vec<_>>();
+    let mut failed = vec![];
+    for (id, definition) in &loaded {
+        match asset_server.get_recursive_dependency_load_state(*id) {
+            Some(RecursiveDependencyLoadState::Loaded) => {},
+            Some(RecursiveDependencyLoadState::Failed) => failed.push(SvarogError::LoadFailed { path: definition.path().to_string() }),
+            _ => return,
+        }
+    }
+    reported.extend(failed);
+
+    // fonts first, then tilesets, then grids, so that reading each can rely on the ones before
+    loaded.sort_by_key(|(_, definition)| match definition { Definition::Font(_) => 0, Definition::Tilesets(_) => 1, Definition::Grids(_) => 2 });
+    for (id, definition) in loaded {
+        let Some(file) = files.get(id) else { continue; };
+        match definition {
+            Definition::Font(path) => reported.extend(fonts.load(path, &file.text)),
+            Definition::Tilesets(path) => {
+                reported.extend(tilesets.load(path, &file.text));
+                for (font, handle) in &file.fonts {
+                    if let Some(font_file) = files.get(handle) {
+                        reported.extend(fonts.load(font, &font_file.text));
+                    }
+                }
+            },
+            Definition::Grids(path) => reported.extend(grids.load(path, &file.text).1),
+        }
+    }
+
     next.set(GameStates::asset_loading_state());
 }
 
@@ -515,83 +1105,226 @@ pub fn create_camera(mut commands: Commands) {
         CameraTag));
 }
 
+/// Spawns the entity a glyph grid's chunks get parented to; the chunks themselves are spawned as they come into view
+pub fn spawn_grid(commands: &mut Commands, grid: &mut Grid, atlas: Handle<TextureAtlas>, pos: Vec3) -> Entity {
+    grid.chunks.clear();
+    grid.atlas = Some(atlas);
+
+    let id = commands
+        .spawn((
+            GridTag(grid.name.to_string()),
+            Transform::from_translation(pos),
+            GlobalTransform::default(),
+            Visibility::Visible,
+            InheritedVisibility::default(),
+        )).id();
+
+    grid.entity = Some(id);
+    id
+}
+
+/// Spawns a glyph grid where its alignment puts it, parented to the camera if it's aligned to the window
+pub fn place_grid(commands: &mut Commands, grid: &mut Grid, tileset: &Tileset, atlas: Handle<TextureAtlas>, view: Vec2, camera: Entity) -> Entity {
+    let position = grid.align(tileset, view);
+    let id = spawn_grid(commands, grid, atlas, position.unwrap_or(Vec3::ZERO));
+    if position.is_some() {
+        commands.entity(camera).push_children(&[id]);
+    }
+    id
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn create_grid_entities<GameAssets: SvarogTextureAtlases, GameStates: SvarogStates>(
     mut commands: Commands, 
     mut grids: ResMut<Grids>,
     assets: Res<GameAssets>, 
-    tilesets: Res<Tilesets>, 
+    mut tilesets: ResMut<Tilesets>, 
+    scale: Res<SvarogScale>,
     window: Query<&Window, With<PrimaryWindow>>,
     camera: Query<Entity, With<CameraTag>>,
+    mut reported: ResMut<Reported>,
     mut next: ResMut<NextState<GameStates>>) {
 
-    let Ok(window) = window.get_single() else { println!("NO WINDOW!"); return; };
-    let Ok(camera) = camera.get_single() else { println!("NO CAMERA!"); return; };
+    // the game goes on even if grids can't be placed, with whatever went wrong reported
+    next.set(GameStates::done_loading_state());
+    tilesets.attach_atlases(&*assets);
+
+    let (Ok(window), Ok(camera)) = (window.get_single(), camera.get_single()) else {
+        reported.report(SvarogError::NoWindow);
+        return;
+    };
+    let view = scale.view_of(window);
 
-    for (_, grid) in &mut grids.grids {
+    for grid in grids.grids.values_mut() {
         if grid.kind == GridKind::Glyph {
             let Some(tileset) = tilesets.tilesets.get(&grid.tileset) else { 
-                println!("NO TILESET: {}", grid.tileset);
-                return; 
+                reported.report(SvarogError::UnknownTileset { at: Some(grid.defined_at.clone()), tileset: grid.tileset.clone() });
+                continue; 
             };
 
-            let (pos, camera_aligned) = {
-                if let Some(pos) = grid.align(tileset, window) {
-                    (pos, true)
-                } else {
-                    (Vec3::ZERO, false)
-                }
+            if grid.fill_width || grid.fill_height {
+                let (width, height) = grid.fill_size(tileset, view);
+                grid.resize(width, height);
+            }
+
+            let Some(atlas) = assets.get(&tileset.name) else {
+                reported.report(SvarogError::MissingAtlas { tileset: tileset.name.clone() });
+                continue;
             };
+            place_grid(&mut commands, grid, tileset, atlas, view, camera);
+        }
+    }
+}
 
-            let id = commands
-                .spawn((
-                    GridTag(grid.name.to_string()),
-                    Transform::from_translation(pos),
-                    GlobalTransform::default(),
-                    Visibility::Visible,
-                    InheritedVisibility::default(),
-                ))
-                .with_children(|f| {
-                    for j in 0..grid.height {
-                        for i in 0..grid.width {
-                            let handle = f.spawn((SpriteSheetBundle {
-                                sprite: TextureAtlasSprite { index: 0, ..Default::default() },
-                                texture_atlas: assets.get(&tileset.name).unwrap_or_else(|| panic!("NO FONT: {}", tileset.name)),
-                                transform: Transform::from_translation(Vec3::new(
-                                    ((grid.x + i) * tileset.width) as f32, 
-                                    ((if grid.align == GridAlign::None { grid.y } else { 0 } + j) * tileset.height) as f32, 
-                                    grid.depth as f32)),
-                                visibility: Visibility::Hidden,
-                                ..Default::default()
-                            },)).id();
-    
-                            grid.entities.push(handle);
-                        }
-                    }
-                }).id();
+/// Keeps camera-aligned grids in place when the window or the scale change, and resizes `fill` grids to match
+#[allow(clippy::too_many_arguments)]
+pub fn follow_window_size<GameAssets: SvarogTextureAtlases>(
+    mut commands: Commands,
+    mut resized: EventReader<WindowResized>,
+    mut grids: ResMut<Grids>,
+    mut window_size: ResMut<SvarogWindowSize>,
+    scale: Res<SvarogScale>,
+    assets: Res<GameAssets>,
+    tilesets: Res<Tilesets>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<Entity, With<CameraTag>>,
+    mut transforms: Query<&mut Transform, With<GridTag>>,
+    mut reported: ResMut<Reported>) {
+
+    let was_resized = resized.read().last().is_some();
+    if !was_resized && !scale.is_changed() {
+        return;
+    }
+
+    let Ok(window) = window.get_single() else { return; };
+    let Ok(camera) = camera.get_single() else { return; };
+    *window_size = SvarogWindowSize(window.width() as u32, window.height() as u32);
+    let view = scale.view_of(window);
 
-            grid.entity = Some(id);
+    for grid in grids.grids.values_mut() {
+        if grid.kind != GridKind::Glyph {
+            continue;
+        }
+
+        let Some(tileset) = tilesets.tilesets.get(&grid.tileset) else { continue; };
+
+        if grid.fill_width || grid.fill_height {
+            let (width, height) = grid.fill_size(tileset, view);
+            if (width, height) != (grid.width, grid.height) {
+                grid.resize(width, height);
+
+                if let Some(old) = grid.entity.take() {
+                    commands.entity(old).despawn_recursive();
+                }
 
-            if camera_aligned {
-                commands.entity(camera).push_children(&[id]);
+                let Some(atlas) = assets.get(&tileset.name) else {
+                    reported.report(SvarogError::MissingAtlas { tileset: tileset.name.clone() });
+                    continue;
+                };
+                place_grid(&mut commands, grid, tileset, atlas, view, camera);
+                continue;
+            }
+        }
+
+        if let (Some(entity), Some(position)) = (grid.entity, grid.align(tileset, view)) {
+            if let Ok(mut transform) = transforms.get_mut(entity) {
+                transform.translation = position;
             }
         }
     }
+}
 
-    next.set(GameStates::done_loading_state());
+/// Checks the definitions against each other: the tilesets grids use, the fonts of tilesets, glyphs of fonts against
+/// the atlases of the tilesets using them, and glyphs that were already put in cells against their fonts
+pub fn validate(tilesets: &Tilesets, fonts: &Fonts, grids: &Grids) -> Vec<SvarogError> {
+    let mut errors = vec![];
+
+    let mut names = tilesets.tilesets.keys().collect::<Vec<_>>();
+    names.sort();
+    for tileset in names.into_iter().map(|name| &tilesets.tilesets[name]) {
+        let Some(font) = fonts.fonts.get(&tileset.font) else {
+            errors.push(SvarogError::UnknownFont { tileset: tileset.name.clone(), font: tileset.font.clone() });
+            continue;
+        };
+
+        let mut glyphs = font.glyphs.values().collect::<Vec<_>>();
+        glyphs.sort_by_key(|glyph| font.lines.get(&glyph.name).copied());
+        for glyph in glyphs {
+            if glyph.x < 1 || glyph.y < 1 || glyph.x > tileset.columns || glyph.y > tileset.rows {
+                errors.push(SvarogError::GlyphOutOfAtlas {
+                    at: Location::new(&tileset.font, font.lines.get(&glyph.name).copied().unwrap_or_default()),
+                    glyph: glyph.name.clone(),
+                    x: glyph.x,
+                    y: glyph.y,
+                    tileset: tileset.name.clone(),
+                    columns: tileset.columns,
+                    rows: tileset.rows,
+                });
+            }
+        }
+    }
+
+    let mut strings = strings().lock().unwrap();
+    let mut names = grids.grids.keys().collect::<Vec<_>>();
+    names.sort();
+    for grid in names.into_iter().map(|name| &grids.grids[name]) {
+        if grid.kind != GridKind::Glyph {
+            continue;
+        }
+
+        if !tilesets.tilesets.contains_key(&grid.tileset) {
+            errors.push(SvarogError::UnknownTileset { at: Some(grid.defined_at.clone()), tileset: grid.tileset.clone() });
+            continue;
+        }
+
+        let mut missing = HashSet::new();
+        for cell in grid.cells.iter().filter(|cell| !cell.is_empty()) {
+            let (Some(tileset), Some(glyph)) = (strings.out(cell.tileset).cloned(), strings.out(cell.glyph).cloned()) else { continue; };
+            if tilesets.tilesets.contains_key(&tileset) && fonts.glyph(tilesets, &tileset, &glyph).is_none() && missing.insert((tileset.clone(), glyph.clone())) {
+                errors.push(SvarogError::MissingGlyph { grid: Some(grid.name.clone()), tileset, glyph });
+            }
+        }
+    }
+
+    errors
+}
+
+/// Hands the problems grid methods ran into this frame over to `Reported`
+pub fn report_grid_errors(mut grids: ResMut<Grids>, mut reported: ResMut<Reported>) {
+    if !grids.errors.is_empty() {
+        reported.extend(std::mem::take(&mut grids.errors));
+    }
+}
+
+/// Reports whatever `validate` finds once static loading is done. In strict mode, startup fails if anything
+/// was reported up to this point.
+pub fn validate_definitions(tilesets: Res<Tilesets>, fonts: Res<Fonts>, grids: Res<Grids>, strict: Option<Res<StrictMode>>, mut reported: ResMut<Reported>) {
+    reported.extend(validate(&tilesets, &fonts, &grids));
+
+    let count = reported.errors.len();
+    if count > 0 && strict.is_some_and(|strict| strict.0) {
+        panic!("Strict mode: {} problems were found while loading", count);
+    }
 }
 
 impl<A: SvarogTextureAtlases, S: SvarogStates> Plugin for SvarogLoadingPlugin<A, S> {
     fn build(&self, app: &mut bevy::prelude::App) {
         let mut tilesets = Tilesets::default();
         let mut fonts = Fonts::default();
-        let mut grids = Grids::default();
+        let mut grids = Grids {
+            default_tileset: app.world.get_resource::<SvarogConfig>().map(|config| config.tileset.clone()).unwrap_or_default(),
+            ..Default::default()
+        };
 
         (self.loader.as_ref().expect("Expected loader function"))(&mut tilesets, &mut fonts, &mut grids);
         app.insert_resource(tilesets);
         app.insert_resource(fonts);
         app.insert_resource(grids);
-        app.add_systems(OnEnter(S::static_loading_state()), start_static_loading::<S>);
-        app.add_systems(OnEnter(S::static_loading_state()), create_camera);
+        app.init_resource::<Reported>();
+        app.add_systems(Last, report_grid_errors);
+        app.add_systems(OnEnter(S::static_loading_state()), (create_camera, watch_definitions));
+        app.add_systems(Update, (watch_definitions, finish_static_loading::<S>).chain().run_if(in_state(S::static_loading_state())));
+        app.add_systems(OnEnter(S::asset_loading_state()), validate_definitions);
  
         app.add_state::<S>().add_loading_state(
             LoadingState::new(S::asset_loading_state())
@@ -601,5 +1334,121 @@ impl<A: SvarogTextureAtlases, S: SvarogStates> Plugin for SvarogLoadingPlugin<A,
         );
 
         app.add_systems(OnEnter(S::setup_state()), create_grid_entities::<A, S>);
+        app.add_systems(Update, follow_window_size::<A>.run_if(in_state(S::done_loading_state())));
+        app.add_plugins(SvarogReloadPlugin::<A, S>::default());
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod loading_testing {
+    use bevy::ecs::entity::Entity;
+
+    use crate::errors::Reported;
+
+    use super::{validate, Fonts, GridAlign, Grids, Tileset, Tilesets};
+
+    const BEFORE: &str = "name | width | height | depth | x | y | kind  | tileset | align\n\
+                          map  |    10 |      5 |     0 | 0 | 0 | glyph | oryx    | None\n\
+                          hud  |     4 |      1 |    10 | 0 | 0 | glyph | oryx    | TopLeft\n";
+
+    const AFTER: &str = "name | width | height | depth | x | y | kind  | tileset | align\n\
+                         map  |    12 |      5 |     3 | 0 | 0 | glyph | oryx    | Center\n";
+
+    #[test]
+    fn test_reloading_tilesets_drops_removed_ones() {
+        let mut tilesets = Tilesets::default();
+        tilesets.load("tilesets.csv", "name | font | weight | width | height | columns | rows\n\
+                                       ascii | ascii.font.csv | 1 | 8 | 8 | 16 | 16\n\
+                                       oryx  | oryx.font.csv  | 1 | 24 | 24 | 8 | 8\n");
+        tilesets.load("tilesets.csv", "name | font | weight | width | height | columns | rows\n\
+                                       oryx  | oryx.font.csv  | 1 | 24 | 24 | 8 | 8\n");
+
+        assert!(!tilesets.tilesets.contains_key("ascii"));
+        assert!(tilesets.tilesets.contains_key("oryx"));
+        assert_eq!(tilesets.sources["tilesets.csv"], vec![ "oryx".to_string() ]);
+    }
+
+    #[test]
+    fn test_reloading_grids_keeps_contents() {
+        let mut grids = Grids::default();
+        grids.load("grids.csv", BEFORE);
+        grids.set("map", 2, 3, "door");
+        grids.grids.get_mut("map").unwrap().entity = Some(Entity::from_raw(7));
+
+        let (stale, _) = grids.load("grids.csv", AFTER);
+        assert_eq!(stale, vec![ Entity::from_raw(7) ]);
+        assert!(!grids.grids.contains_key("hud"));
+
+        let map = &grids.grids["map"];
+        assert_eq!((map.width, map.depth, &map.align), (12, 3, &GridAlign::Center));
+        assert_eq!(grids.get("map", 2, 3).and_then(|cell| cell.glyph_name()), Some("door".to_string()));
+    }
+
+    #[test]
+    fn test_glyph_tags_split_on_commas() {
+        let mut fonts = Fonts::default();
+        fonts.load("tiny.font.csv", "name | x | y | attributes\n\
+                                     wall | 1 | 1 | wall, brick; cost=2\n");
+
+        let wall = &fonts.fonts["tiny.font.csv"].glyphs["wall"];
+        assert_eq!(wall.attributes, vec![ "wall, brick", "cost=2" ]);
+        assert_eq!(wall.tags().collect::<Vec<_>>(), vec![ "wall", "brick", "cost=2" ]);
+        assert!(wall.has_tag("brick") && !wall.has_tag("door"));
+    }
+
+    #[test]
+    fn test_loading_returns_problems() {
+        let mut fonts = Fonts::default();
+        let errors = fonts.load("tiny.font.csv", "name | x | y | attributes\n\
+                                                  wall | 1 | 1 | solid\n\
+                                                  wall | 2 | 1 | solid\n");
+        assert_eq!(errors.iter().map(|error| error.to_string()).collect::<Vec<_>>(), vec![ "tiny.font.csv:3: glyph wall is defined again" ]);
+
+        let mut grids = Grids::default();
+        let text = "name | width | height | depth | x | y | kind  | tileset | align\n\
+                    map  |  wide |      4 |     0 | 0 | 0 | glyph | tiny    | None\n";
+        let (_, errors) = grids.load("grids.csv", text);
+        assert_eq!(errors.iter().map(|error| error.to_string()).collect::<Vec<_>>(),
+            vec![ "grids.csv:2: malformed row: grid map has a size that is neither a number nor fill: wide" ]);
+
+        let mut reported = Reported::default();
+        reported.extend(errors);
+        reported.extend(grids.load("grids.csv", text).1);
+        assert_eq!(reported.errors.len(), 1);
+
+        grids.set("nowhere", 0, 0, "wall");
+        grids.set("map", 0, 9, "wall");
+        assert_eq!(grids.errors.iter().map(|error| error.to_string()).collect::<Vec<_>>(), vec![ "No grid nowhere", "No cell 0, 9 in grid map" ]);
+    }
+
+    #[test]
+    fn test_validation_finds_problems() {
+        let mut fonts = Fonts::default();
+        fonts.load("tiny.font.csv", "name | x | y | attributes\n\
+                                     wall | 1 | 1 | solid\n\
+                                     door | 3 | 1 | \n");
+
+        let mut tilesets = Tilesets::default();
+        tilesets.tilesets.insert("tiny".to_string(), Tileset {
+            name: "tiny".to_string(),
+            font: "tiny.font.csv".to_string(),
+            weight: 0,
+            width: 8,
+            height: 8,
+            columns: 2,
+            rows: 1,
+        });
+
+        let mut grids = Grids::default();
+        grids.load("grids.csv", "name | width | height | depth | x | y | kind  | tileset | align\n\
+                                 map  |     4 |      4 |     0 | 0 | 0 | glyph | tiny    | None\n\
+                                 hud  |     4 |      1 |     0 | 0 | 0 | glyph | huge    | None\n");
+        grids.set("map", 0, 0, "window");
+
+        let errors = validate(&tilesets, &fonts, &grids).iter().map(|error| error.to_string()).collect::<Vec<_>>();
+        assert_eq!(errors, vec![
+            "tiny.font.csv:3: glyph door at 3, 1 is outside of tileset tiny, which is 2 by 1",
+            "grids.csv:3: unknown tileset huge",
+            "Grid map uses glyph window, which tileset tiny doesn't have",
+        ]);
+    }
+}
@@ -0,0 +1,339 @@
+use std::{cmp::Reverse, collections::HashMap, marker::PhantomData};
+
+use bevy::{app::{Plugin, PostUpdate}, ecs::{schedule::{common_conditions::in_state, IntoSystemConfigs}, system::{Res, ResMut, Resource}}};
+use pathfinding::prelude::astar;
+use priority_queue::PriorityQueue;
+
+use crate::{loading::{strings, Fonts, Grid, GridKind, Grids, SvarogStates, Tilesets}, update::grid_stream_chunks};
+
+pub type Point = (i32, i32);
+
+const DIRECTIONS: [Point; 8] = [ (0, -1), (1, -1), (1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1) ];
+
+/// The price of stepping into each cell of a grid, with `None` for cells that can't be entered
+#[derive(Debug, Clone, Default)]
+pub struct CostMap {
+    pub width: i32,
+    pub height: i32,
+    pub costs: Vec<Option<u32>>,
+    pub version: u64,
+}
+
+impl CostMap {
+    pub fn new(width: i32, height: i32) -> Self {
+        Self {
+            width,
+            height,
+            costs: vec![ Some(1); (width * height).max(0) as usize ],
+            version: 0,
+        }
+    }
+
+    /// Reads costs from glyph attributes: glyphs tagged with `blocking` are impassable,
+    /// and a `cost=N` attribute makes a glyph take N (at least 1) to step on instead of 1.
+    /// A `Boolean` grid instead makes every set cell impassable.
+    pub fn from_grid(grid: &Grid, tilesets: &Tilesets, fonts: &Fonts, blocking: &str) -> Self {
+        let mut map = CostMap::new(grid.width, grid.height);
+        let mut strings = strings().lock().unwrap();
+
+        if grid.kind == GridKind::Boolean {
+            for y in 0..grid.height {
+                for x in 0..grid.width {
+                    if grid.is_set(x, y) {
+                        map.set(x, y, None);
+                    }
+                }
+            }
+
+            map.version = grid.version;
+            return map;
+        }
+
+        for y in 0..grid.height {
+            for x in 0..grid.width {
+                let Some(cell) = grid.cell(x, y) else { continue; };
+                if cell.is_empty() {
+                    continue;
+                }
+
+                let Some(glyph) = strings.out(cell.glyph).and_then(|name| fonts.glyph(tilesets, &grid.tileset, name)) else { continue; };
+                let cost = if glyph.has_tag(blocking) {
+                    None
+                } else {
+                    Some(glyph.tags()
+                        .find_map(|a| a.strip_prefix("cost=").and_then(|n| n.trim().parse::<u32>().ok()))
+                        .unwrap_or(1))
+                };
+
+                map.set(x, y, cost);
+            }
+        }
+
+        map.version = grid.version;
+        map
+    }
+
+    pub fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            None
+        } else {
+            Some((y * self.width + x) as usize)
+        }
+    }
+
+    pub fn cost(&self, x: i32, y: i32) -> Option<u32> {
+        self.index(x, y).and_then(|i| self.costs[i])
+    }
+
+    /// Costs are clamped to at least 1, since the A* heuristic counts every step as 1
+    pub fn set(&mut self, x: i32, y: i32, cost: Option<u32>) {
+        if let Some(i) = self.index(x, y) {
+            self.costs[i] = cost.map(|cost| cost.max(1));
+        }
+    }
+
+    pub fn is_passable(&self, x: i32, y: i32) -> bool {
+        self.cost(x, y).is_some()
+    }
+
+    pub fn neighbours(&self, (x, y): Point) -> impl Iterator<Item = (Point, u32)> + '_ {
+        DIRECTIONS.iter().filter_map(move |(dx, dy)| {
+            let next = (x + dx, y + dy);
+            self.cost(next.0, next.1).map(|cost| (next, cost))
+        })
+    }
+
+    /// Cheapest path between two cells using A*, including both ends, along with its total cost
+    pub fn path(&self, from: Point, to: Point) -> Option<(Vec<Point>, u32)> {
+        if !self.is_passable(to.0, to.1) {
+            return None;
+        }
+
+        astar(
+            &from,
+            |&p| self.neighbours(p).collect::<Vec<_>>(),
+            |&(x, y)| (x - to.0).unsigned_abs().max((y - to.1).unsigned_abs()),
+            |&p| p == to)
+    }
+
+    /// Distance from every cell to the nearest of the goals
+    pub fn dijkstra(&self, goals: &[Point]) -> DijkstraMap {
+        let mut distances = vec![ None; self.costs.len() ];
+        let mut queue = PriorityQueue::new();
+
+        for &(x, y) in goals {
+            if let Some(i) = self.index(x, y) {
+                distances[i] = Some(0);
+                queue.push((x, y), Reverse(0u32));
+            }
+        }
+
+        while let Some((point, Reverse(distance))) = queue.pop() {
+            for (next, cost) in self.neighbours(point) {
+                let i = self.index(next.0, next.1).unwrap();
+                let candidate = distance + cost;
+                if distances[i].map(|d| candidate < d).unwrap_or(true) {
+                    distances[i] = Some(candidate);
+                    queue.push_increase(next, Reverse(candidate));
+                }
+            }
+        }
+
+        DijkstraMap {
+            width: self.width,
+            height: self.height,
+            distances,
+            version: self.version,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DijkstraMap {
+    pub width: i32,
+    pub height: i32,
+    pub distances: Vec<Option<u32>>,
+    pub version: u64,
+}
+
+impl DijkstraMap {
+    pub fn distance(&self, x: i32, y: i32) -> Option<u32> {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            None
+        } else {
+            self.distances[(y * self.width + x) as usize]
+        }
+    }
+
+    /// The neighbouring cell that gets closest to a goal, or `None` if already there or stuck
+    pub fn downhill(&self, x: i32, y: i32) -> Option<Point> {
+        let here = self.distance(x, y)?;
+        DIRECTIONS.iter()
+            .map(|(dx, dy)| (x + dx, y + dy))
+            .filter_map(|(nx, ny)| self.distance(nx, ny).map(|d| ((nx, ny), d)))
+            .filter(|(_, d)| *d < here)
+            .min_by_key(|(_, d)| *d)
+            .map(|(p, _)| p)
+    }
+
+    /// The neighbouring cell that gets furthest away from every goal, for fleeing
+    pub fn uphill(&self, x: i32, y: i32) -> Option<Point> {
+        let here = self.distance(x, y)?;
+        DIRECTIONS.iter()
+            .map(|(dx, dy)| (x + dx, y + dy))
+            .filter_map(|(nx, ny)| self.distance(nx, ny).map(|d| ((nx, ny), d)))
+            .filter(|(_, d)| *d > here)
+            .max_by_key(|(_, d)| *d)
+            .map(|(p, _)| p)
+    }
+
+    /// Step direction for every cell, following `downhill`
+    pub fn flow_field(&self) -> Vec<Option<Point>> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| self.downhill(x, y).map(|(nx, ny)| (nx - x, ny - y)))
+            .collect()
+    }
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct NavigationSettings {
+    /// The grid that is walked on
+    pub grid: String,
+    /// Glyphs tagged with this attribute can't be walked through
+    pub blocking: String,
+}
+
+/// Costs of the navigation grid kept in sync with its cells, plus named Dijkstra maps shared between systems
+#[derive(Resource, Default, Debug)]
+pub struct Navigation {
+    pub costs: CostMap,
+    maps: HashMap<String, (Vec<Point>, DijkstraMap)>,
+}
+
+impl Navigation {
+    pub fn path(&self, from: Point, to: Point) -> Option<Vec<Point>> {
+        self.costs.path(from, to).map(|(path, _)| path)
+    }
+
+    /// Gets the Dijkstra map stored under `name`, recomputing it if the goals or the grid changed since
+    pub fn dijkstra(&mut self, name: &str, goals: &[Point]) -> &DijkstraMap {
+        let stale = match self.maps.get(name) {
+            Some((cached_goals, map)) => cached_goals.as_slice() != goals || map.version != self.costs.version,
+            None => true,
+        };
+
+        if stale {
+            let map = self.costs.dijkstra(goals);
+            self.maps.insert(name.to_string(), (goals.to_vec(), map));
+        }
+
+        &self.maps[name].1
+    }
+
+    pub fn cached(&self, name: &str) -> Option<&DijkstraMap> {
+        self.maps.get(name).map(|(_, map)| map)
+    }
+}
+
+pub fn navigation_sync(settings: Res<NavigationSettings>, grids: Res<Grids>, tilesets: Res<Tilesets>, fonts: Res<Fonts>, mut navigation: ResMut<Navigation>) {
+    let Some(grid) = grids.grids.get(&settings.grid) else { return; };
+    if navigation.costs.version == grid.version && navigation.costs.width == grid.width {
+        return;
+    }
+
+    navigation.costs = CostMap::from_grid(grid, &tilesets, &fonts, &settings.blocking);
+}
+
+pub struct SvarogNavigationPlugin<S: SvarogStates> {
+    settings: NavigationSettings,
+    _marker: PhantomData<S>,
+}
+
+impl<S: SvarogStates> SvarogNavigationPlugin<S> {
+    pub fn new(grid: &str, blocking: &str) -> Self {
+        Self {
+            settings: NavigationSettings { grid: grid.to_string(), blocking: blocking.to_string() },
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<S: SvarogStates> Plugin for SvarogNavigationPlugin<S> {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.insert_resource(self.settings.clone());
+        app.init_resource::<Navigation>();
+        app.add_systems(PostUpdate, navigation_sync
+            .before(grid_stream_chunks)
+            .run_if(in_state(S::done_loading_state())));
+    }
+}
+
+#[cfg(test)]
+mod navigation_testing {
+    use super::CostMap;
+
+    fn walled() -> CostMap {
+        // .....
+        // .###.
+        // .....
+        let mut map = CostMap::new(5, 3);
+        for x in 1..4 {
+            map.set(x, 1, None);
+        }
+        map
+    }
+
+    #[test]
+    fn test_path_goes_around_walls() {
+        let map = walled();
+        let (path, cost) = map.path((0, 1), (4, 1)).unwrap();
+        assert_eq!(path.first(), Some(&(0, 1)));
+        assert_eq!(path.last(), Some(&(4, 1)));
+        assert!(path.iter().all(|&(x, y)| map.is_passable(x, y)));
+        assert_eq!(cost, 4);
+    }
+
+    #[test]
+    fn test_no_path_into_wall() {
+        let map = walled();
+        assert!(map.path((0, 0), (2, 1)).is_none());
+    }
+
+    #[test]
+    fn test_costly_cells_are_avoided() {
+        let mut map = CostMap::new(3, 2);
+        map.set(1, 0, Some(10));
+        let (path, cost) = map.path((0, 0), (2, 0)).unwrap();
+        assert_eq!(path, vec![ (0, 0), (1, 1), (2, 0) ]);
+        assert_eq!(cost, 2);
+    }
+
+    #[test]
+    fn test_free_cells_cost_at_least_one() {
+        let mut map = CostMap::new(3, 1);
+        map.set(1, 0, Some(0));
+        assert_eq!(map.cost(1, 0), Some(1));
+        let (path, cost) = map.path((0, 0), (2, 0)).unwrap();
+        assert_eq!(path, vec![ (0, 0), (1, 0), (2, 0) ]);
+        assert_eq!(cost, 2);
+    }
+
+    #[test]
+    fn test_dijkstra_flows_downhill() {
+        let map = walled();
+        let dijkstra = map.dijkstra(&[ (4, 1) ]);
+        assert_eq!(dijkstra.distance(4, 1), Some(0));
+        assert_eq!(dijkstra.distance(2, 1), None);
+        assert_eq!(dijkstra.distance(0, 1), Some(4));
+
+        let mut at = (0, 1);
+        let mut steps = 0;
+        while let Some(next) = dijkstra.downhill(at.0, at.1) {
+            at = next;
+            steps += 1;
+        }
+        assert_eq!(at, (4, 1));
+        assert_eq!(steps, 4);
+    }
+}
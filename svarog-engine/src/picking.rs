@@ -0,0 +1,156 @@
+use std::marker::PhantomData;
+
+use bevy::{app::{Plugin, Update}, ecs::{event::{Event, EventWriter}, query::With, schedule::{common_conditions::in_state, IntoSystemConfigs}, system::{Query, Res, ResMut, Resource}},
+    input::{mouse::MouseButton, Input}, math::Vec2, render::camera::Camera, transform::components::GlobalTransform, window::{PrimaryWindow, Window}};
+
+use crate::loading::{CameraTag, Grid, GridAlign, GridKind, GridTag, Grids, SvarogStates, Tileset, Tilesets};
+
+/// A cell of a grid under the mouse cursor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GridPick {
+    pub grid: String,
+    pub x: i32,
+    pub y: i32,
+    pub depth: i32,
+}
+
+/// Where the mouse cursor is, in the world and in every glyph grid it's over
+#[derive(Resource, Default, Debug, Clone, PartialEq)]
+pub struct GridCursor {
+    pub world: Option<Vec2>,
+    /// Cells under the cursor, the one in the grid drawn on top first
+    pub picks: Vec<GridPick>,
+}
+
+impl GridCursor {
+    pub fn get(&self, grid: &str) -> Option<(i32, i32)> {
+        self.picks.iter().find(|pick| pick.grid == grid).map(|pick| (pick.x, pick.y))
+    }
+
+    pub fn top(&self) -> Option<&GridPick> {
+        self.picks.first()
+    }
+}
+
+/// Sent when the cell under the cursor changes in a grid; `cell` is `None` once the cursor leaves it
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct GridHover {
+    pub grid: String,
+    pub cell: Option<(i32, i32)>,
+}
+
+/// Sent for each grid under the cursor when a mouse button is pressed
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct GridClick {
+    pub grid: String,
+    pub x: i32,
+    pub y: i32,
+    pub button: MouseButton,
+}
+
+impl Grid {
+    /// The cell at a position relative to the grid's entity, the same way chunks lay out their sprites
+    pub fn cell_at(&self, tileset: &Tileset, local: Vec2) -> Option<(i32, i32)> {
+        let x = (local.x / tileset.width as f32).round() as i32 - self.x;
+        let offset = if self.align == GridAlign::None { self.y } else { 0 };
+        let j = (local.y / tileset.height as f32).round() as i32 - offset;
+        let y = self.height - 1 - j;
+        self.index(x, y).map(|_| (x, y))
+    }
+}
+
+/// Works out which cell of each glyph grid the cursor is over, honoring alignment, the camera and the letterbox
+pub fn grid_cursor(
+    grids: Res<Grids>,
+    tilesets: Res<Tilesets>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform), With<CameraTag>>,
+    transforms: Query<&GlobalTransform, With<GridTag>>,
+    mut cursor: ResMut<GridCursor>,
+    mut hovers: EventWriter<GridHover>,
+) {
+    let world = match (window.get_single(), camera.get_single()) {
+        (Ok(window), Ok((camera, camera_transform))) => window.cursor_position().and_then(|position| {
+            let corner = camera.logical_viewport_rect().map(|rect| rect.min).unwrap_or(Vec2::ZERO);
+            camera.viewport_to_world_2d(camera_transform, position - corner)
+        }),
+        _ => None,
+    };
+
+    let mut picks = Vec::new();
+    if let Some(world) = world {
+        for grid in grids.grids.values().filter(|grid| grid.kind == GridKind::Glyph) {
+            let Some(tileset) = tilesets.tilesets.get(&grid.tileset) else { continue; };
+            let Some(transform) = grid.entity.and_then(|entity| transforms.get(entity).ok()) else { continue; };
+            let local = transform.affine().inverse().transform_point3(world.extend(0.0));
+            if let Some((x, y)) = grid.cell_at(tileset, local.truncate()) {
+                picks.push(GridPick { grid: grid.name.clone(), x, y, depth: grid.depth });
+            }
+        }
+    }
+    picks.sort_by(|a, b| b.depth.cmp(&a.depth).then_with(|| a.grid.cmp(&b.grid)));
+
+    for pick in &picks {
+        if cursor.get(&pick.grid) != Some((pick.x, pick.y)) {
+            hovers.send(GridHover { grid: pick.grid.clone(), cell: Some((pick.x, pick.y)) });
+        }
+    }
+
+    for left in cursor.picks.iter().filter(|old| !picks.iter().any(|pick| pick.grid == old.grid)) {
+        hovers.send(GridHover { grid: left.grid.clone(), cell: None });
+    }
+
+    cursor.set_if_neq(GridCursor { world, picks });
+}
+
+pub fn grid_clicks(buttons: Res<Input<MouseButton>>, cursor: Res<GridCursor>, mut clicks: EventWriter<GridClick>) {
+    for &button in buttons.get_just_pressed() {
+        for pick in &cursor.picks {
+            clicks.send(GridClick { grid: pick.grid.clone(), x: pick.x, y: pick.y, button });
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SvarogPickingPlugin<S: SvarogStates>(PhantomData<S>);
+
+impl<S: SvarogStates> Plugin for SvarogPickingPlugin<S> {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_resource::<GridCursor>()
+            .add_event::<GridHover>()
+            .add_event::<GridClick>()
+            .add_systems(Update, (grid_cursor, grid_clicks)
+                .chain()
+                .run_if(in_state(S::done_loading_state())));
+    }
+}
+
+#[cfg(test)]
+mod picking_testing {
+    use bevy::math::Vec2;
+
+    use crate::loading::{Grid, GridAlign, Tileset};
+
+    fn tileset() -> Tileset {
+        Tileset { name: "tiny".to_string(), font: "tiny".to_string(), weight: 1, width: 10, height: 20, columns: 16, rows: 16 }
+    }
+
+    #[test]
+    fn test_cell_at_inverts_y() {
+        let grid = Grid { width: 4, height: 3, x: 2, y: 1, ..Default::default() };
+
+        // sprites of cell (0, 0) sit at ((2 + 0) * 10, (1 + 2) * 20), the top row drawn highest
+        assert_eq!(grid.cell_at(&tileset(), Vec2::new(20.0, 60.0)), Some((0, 0)));
+        assert_eq!(grid.cell_at(&tileset(), Vec2::new(24.0, 51.0)), Some((0, 0)));
+        assert_eq!(grid.cell_at(&tileset(), Vec2::new(50.0, 20.0)), Some((3, 2)));
+        assert_eq!(grid.cell_at(&tileset(), Vec2::new(60.0, 20.0)), None);
+        assert_eq!(grid.cell_at(&tileset(), Vec2::new(20.0, 0.0)), None);
+    }
+
+    #[test]
+    fn test_cell_at_aligned_grid_ignores_y_offset() {
+        let grid = Grid { width: 4, height: 3, x: 0, y: 5, align: GridAlign::TopLeft, ..Default::default() };
+        assert_eq!(grid.cell_at(&tileset(), Vec2::new(0.0, 40.0)), Some((0, 0)));
+        assert_eq!(grid.cell_at(&tileset(), Vec2::new(30.0, 0.0)), Some((3, 2)));
+    }
+}
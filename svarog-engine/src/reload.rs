@@ -0,0 +1,241 @@
+use std::marker::PhantomData;
+
+use bevy::{app::{Plugin, Update}, asset::{io::Reader, Asset, AssetApp, AssetEvent, AssetId, AssetLoader, AssetServer, Assets, AsyncReadExt, BoxedFuture, Handle, LoadContext, RecursiveDependencyLoadState},
+    ecs::{entity::Entity, event::EventReader, query::With, schedule::{common_conditions::in_state, IntoSystemConfigs}, system::{Commands, Local, Query, Res, ResMut, Resource}},
+    hierarchy::DespawnRecursiveExt, log::info, reflect::TypePath, utils::hashbrown::HashMap, window::{PrimaryWindow, Window}};
+
+use crate::{errors::{Reported, SvarogError}, loading::{definitions, place_grid, CameraTag, Fonts, GridKind, Grids, SvarogStates, SvarogTextureAtlases, Tilesets}, windows::SvarogScale};
+
+/// The text of a definition file, loaded through the asset server both at startup and whenever the file changes
+#[derive(Asset, TypePath, Debug)]
+pub struct DefinitionFile {
+    pub text: String,
+    /// Fonts named in the `font` column of a tilesets table, loaded as dependencies of the file
+    pub fonts: Vec<(String, Handle<DefinitionFile>)>,
+}
+
+#[derive(Default)]
+pub struct DefinitionFileLoader;
+
+impl AssetLoader for DefinitionFileLoader {
+    type Asset = DefinitionFile;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut text = String::new();
+            reader.read_to_string(&mut text).await?;
+
+            // rows that can't be read are reported when the tilesets themselves are read
+            let mut fonts = vec![];
+            let mut reader = definitions(&text);
+            if let Some(column) = reader.headers().ok().and_then(|headers| headers.iter().position(|header| header == "font")) {
+                for record in reader.records().flatten() {
+                    if let Some(font) = record.get(column).filter(|font| !font.is_empty() && !fonts.iter().any(|(name, _)| name == font)) {
+                        fonts.push((font.to_string(), load_context.load(font.to_string())));
+                    }
+                }
+            }
+
+            Ok(DefinitionFile { text, fonts })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["csv"]
+    }
+}
+
+/// What a watched definition file defines, with its path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Definition {
+    Tilesets(String),
+    Font(String),
+    Grids(String),
+}
+
+impl Definition {
+    pub fn path(&self) -> &str {
+        match self {
+            Definition::Tilesets(path) | Definition::Font(path) | Definition::Grids(path) => path,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct WatchedDefinitions {
+    handles: HashMap<String, Handle<DefinitionFile>>,
+    files: HashMap<AssetId<DefinitionFile>, Definition>,
+}
+
+impl WatchedDefinitions {
+    pub fn definitions(&self) -> impl Iterator<Item = (AssetId<DefinitionFile>, &Definition)> {
+        self.files.iter().map(|(id, definition)| (*id, definition))
+    }
+}
+
+/// Loads and watches every definition file that was asked for, including fonts picked up by tilesets
+pub fn watch_definitions(
+    asset_server: Res<AssetServer>,
+    tilesets: Res<Tilesets>,
+    fonts: Res<Fonts>,
+    grids: Res<Grids>,
+    mut watched: ResMut<WatchedDefinitions>,
+) {
+    let mut watch = |path: &String, definition: fn(String) -> Definition| {
+        if watched.handles.contains_key(path) {
+            return;
+        }
+
+        let handle = asset_server.load::<DefinitionFile>(path.clone());
+        watched.files.insert(handle.id(), definition(path.clone()));
+        watched.handles.insert(path.clone(), handle);
+    };
+
+    for path in tilesets.sources.keys() {
+        watch(path, Definition::Tilesets);
+    }
+
+    for path in fonts.fonts.keys() {
+        watch(path, Definition::Font);
+    }
+
+    for path in grids.sources.keys() {
+        watch(path, Definition::Grids);
+    }
+}
+
+/// Takes the entities of the grids whose tileset passes the filter, so they get spawned again
+fn detach_grids(grids: &mut Grids, uses: impl Fn(&str) -> bool) -> Vec<Entity> {
+    grids.grids.values_mut()
+        .filter(|grid| uses(&grid.tileset))
+        .filter_map(|grid| grid.entity.take())
+        .collect()
+}
+
+/// Reads definition files again when they change on disk, and despawns the grids they affect.
+/// Tilesets files wait until the fonts they picked up are loaded too.
+#[allow(clippy::too_many_arguments)]
+pub fn reload_definitions(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<DefinitionFile>>,
+    asset_server: Res<AssetServer>,
+    files: Res<Assets<DefinitionFile>>,
+    watched: Res<WatchedDefinitions>,
+    mut pending: Local<Vec<AssetId<DefinitionFile>>>,
+    mut tilesets: ResMut<Tilesets>,
+    mut fonts: ResMut<Fonts>,
+    mut grids: ResMut<Grids>,
+    mut reported: ResMut<Reported>,
+) {
+    for event in events.read() {
+        let AssetEvent::Modified { id } = event else { continue; };
+        if watched.files.contains_key(id) && !pending.contains(id) {
+            pending.push(*id);
+        }
+    }
+
+    let ready = pending.iter()
+        .copied()
+        .filter(|id| matches!(asset_server.get_recursive_dependency_load_state(*id), Some(RecursiveDependencyLoadState::Loaded | RecursiveDependencyLoadState::Failed)))
+        .collect::<Vec<_>>();
+    pending.retain(|id| !ready.contains(id));
+
+    for id in ready {
+        let (Some(definition), Some(file)) = (watched.files.get(&id), files.get(id)) else { continue; };
+
+        let stale = match definition {
+            Definition::Grids(path) => {
+                let (stale, errors) = grids.load(path, &file.text);
+                reported.extend(errors);
+                stale
+            },
+            Definition::Tilesets(path) => {
+                reported.extend(tilesets.load(path, &file.text));
+                for (font, handle) in &file.fonts {
+                    if let Some(font_file) = files.get(handle) {
+                        reported.extend(fonts.load(font, &font_file.text));
+                    }
+                }
+                detach_grids(&mut grids, |_| true)
+            },
+            Definition::Font(path) => {
+                reported.extend(fonts.load(path, &file.text));
+                detach_grids(&mut grids, |tileset| tilesets.tilesets.get(tileset).is_some_and(|tileset| &tileset.font == path))
+            },
+        };
+
+        info!("Reloaded {}", definition.path());
+        for entity in stale {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Spawns glyph grids that have no entity, which is how reloaded and newly defined grids end up.
+/// Tilesets they use need a texture atlas in the asset collection, which doesn't get reloaded.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_missing_grids<GameAssets: SvarogTextureAtlases>(
+    mut commands: Commands,
+    mut grids: ResMut<Grids>,
+    assets: Res<GameAssets>,
+    mut tilesets: ResMut<Tilesets>,
+    scale: Res<SvarogScale>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<Entity, With<CameraTag>>,
+    mut reported: ResMut<Reported>,
+) {
+    if !grids.grids.values().any(|grid| grid.kind == GridKind::Glyph && grid.entity.is_none()) {
+        return;
+    }
+
+    tilesets.attach_atlases(&*assets);
+
+    let Ok(window) = window.get_single() else { return; };
+    let Ok(camera) = camera.get_single() else { return; };
+    let view = scale.view_of(window);
+
+    for grid in grids.grids.values_mut().filter(|grid| grid.kind == GridKind::Glyph && grid.entity.is_none()) {
+        let Some(tileset) = tilesets.tilesets.get(&grid.tileset) else { continue; };
+
+        if grid.fill_width || grid.fill_height {
+            let (width, height) = grid.fill_size(tileset, view);
+            if (width, height) != (grid.width, grid.height) {
+                grid.resize(width, height);
+            }
+        }
+
+        let Some(atlas) = assets.get(&tileset.name) else {
+            reported.report(SvarogError::MissingAtlas { tileset: tileset.name.clone() });
+            continue;
+        };
+
+        place_grid(&mut commands, grid, tileset, atlas, view, camera);
+    }
+}
+
+/// Watches `tilesets.csv`, `grids.csv` and font files, and applies changes to them while the game runs
+pub struct SvarogReloadPlugin<A: SvarogTextureAtlases, S: SvarogStates>(PhantomData<(A, S)>);
+
+impl<A: SvarogTextureAtlases, S: SvarogStates> Default for SvarogReloadPlugin<A, S> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<A: SvarogTextureAtlases, S: SvarogStates> Plugin for SvarogReloadPlugin<A, S> {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_asset::<DefinitionFile>()
+            .register_asset_loader(DefinitionFileLoader)
+            .init_resource::<WatchedDefinitions>()
+            .add_systems(Update, (watch_definitions, reload_definitions, spawn_missing_grids::<A>)
+                .chain()
+                .run_if(in_state(S::done_loading_state())));
+    }
+}
@@ -1,40 +1,241 @@
-// use bevy::{asset::AssetLoader, prelude::*, utils::thiserror::Error};
-// use rexpaint::XpFile;
-// use serde::Deserialize;
-
-
-// #[derive(Asset, TypePath, Debug, Deserialize)]
-// pub struct RexpaintDocument(pub XpFile);
-
-// #[non_exhaustive]
-// #[derive(Debug, Error)]
-// pub enum RexpaintLoaderError {
-
-// }
-
-// #[derive(Default)]
-// pub struct RexpaintDocumentLoader;
-
-// impl AssetLoader for RexpaintDocumentLoader {
-//     type Asset = RexpaintDocument;
-//     type Settings = ();
-//     type Error = RexpaintLoaderError;
-
-//     fn load<'a>(
-//         &'a self,
-//         reader: &'a mut bevy::asset::io::Reader,
-//         settings: &'a Self::Settings,
-//         load_context: &'a mut bevy::asset::LoadContext,
-//     ) -> bevy::utils::BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
-//         Box::pin(async move {
-//             let mut bytes = Vec::new();
-//             reader.read_to_end(&mut bytes).await?;
-//             let rexpaint_doc = XpFile::read(&mut bytes)?;
-//             Ok(RexpaintDocument(rexpaint_doc))
-//         })
-//     }
-
-//     fn extensions(&self) -> &[&str] {
-//         &["xp"]
-//     }
-// }
\ No newline at end of file
+use std::fmt::Display;
+
+use bevy::{app::{App, Plugin}, asset::{io::Reader, Asset, AssetApp, AssetLoader, AsyncReadExt, BoxedFuture, LoadContext}, reflect::TypePath,
+    render::color::Color};
+use rexpaint::{XpColor, XpFile, XpLayer};
+
+use crate::{errors::SvarogError, loading::{Font, Fonts, Grids, Tilesets}};
+
+#[derive(Asset, TypePath, Debug)]
+pub struct RexpaintDocument(pub XpFile);
+
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum RexpaintLoaderError {
+    Io(std::io::Error),
+}
+
+impl Display for RexpaintLoaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RexpaintLoaderError::Io(error) => write!(f, "Could not read REXPaint document: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for RexpaintLoaderError {}
+
+impl From<std::io::Error> for RexpaintLoaderError {
+    fn from(error: std::io::Error) -> Self {
+        RexpaintLoaderError::Io(error)
+    }
+}
+
+#[derive(Default)]
+pub struct RexpaintDocumentLoader;
+
+impl AssetLoader for RexpaintDocumentLoader {
+    type Asset = RexpaintDocument;
+    type Settings = ();
+    type Error = RexpaintLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let rexpaint_doc = XpFile::read(&mut bytes.as_slice())?;
+            Ok(RexpaintDocument(rexpaint_doc))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["xp"]
+    }
+}
+
+pub struct SvarogRexPlugin;
+
+impl Plugin for SvarogRexPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<RexpaintDocument>()
+            .init_asset_loader::<RexpaintDocumentLoader>();
+    }
+}
+
+/// Code page 437 as REXPaint uses it, indexed by the code stored in each `XpCell`
+const CP437: [char; 256] = [
+    '\0', '☺', '☻', '♥', '♦', '♣', '♠', '•', '◘', '○', '◙', '♂', '♀', '♪', '♫', '☼',
+    '►', '◄', '↕', '‼', '¶', '§', '▬', '↨', '↑', '↓', '→', '←', '∟', '↔', '▲', '▼',
+    ' ', '!', '"', '#', '$', '%', '&', '\'', '(', ')', '*', '+', ',', '-', '.', '/',
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', ':', ';', '<', '=', '>', '?',
+    '@', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O',
+    'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z', '[', '\\', ']', '^', '_',
+    '`', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o',
+    'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', '{', '|', '}', '~', '⌂',
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐',
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧',
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀',
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩',
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}',
+];
+
+/// Frame pieces `GridEditor::frame` draws, for fonts that have no glyph of their own for a CP437 code.
+/// Lines get `None`, as whether they are a top or bottom (left or right) edge depends on the corners around them.
+fn frame_piece(code: u32) -> Option<&'static str> {
+    match code {
+        0xDA | 0xC9 | 0xD5 | 0xD6 => Some("topleft"),
+        0xBF | 0xBB | 0xB8 | 0xB7 => Some("topright"),
+        0xC0 | 0xC8 | 0xD4 | 0xD3 => Some("bottomleft"),
+        0xD9 | 0xBC | 0xBE | 0xBD => Some("bottomright"),
+        0xDB => Some("block"),
+        _ => None,
+    }
+}
+
+fn is_horizontal(code: u32) -> bool {
+    code == 0xC4 || code == 0xCD
+}
+
+fn is_vertical(code: u32) -> bool {
+    code == 0xB3 || code == 0xBA
+}
+
+/// The first corner reached walking from (i, j) by (di, dj) along a line of the same direction
+fn nearest_corner(layer: &XpLayer, i: usize, j: usize, di: i32, dj: i32, along: fn(u32) -> bool) -> Option<&'static str> {
+    let (mut i, mut j) = (i as i32 + di, j as i32 + dj);
+    while i >= 0 && j >= 0 && (i as usize) < layer.width && (j as usize) < layer.height {
+        let code = layer.cells.get(i as usize * layer.height + j as usize)?.ch;
+        if !along(code) {
+            return frame_piece(code).filter(|piece| *piece != "block");
+        }
+
+        i += di;
+        j += dj;
+    }
+
+    None
+}
+
+/// Finds the glyph of `font` that draws the CP437 code of the cell at (i, j): the one tagged `cp437=NN`,
+/// or else the one named after the character itself, like in `sourcecodepro.font.csv`. Fonts with neither
+/// fall back to frame pieces, lines facing the way of the nearest corner along them.
+pub fn cp437_glyph(font: Option<&Font>, layer: &XpLayer, i: usize, j: usize) -> String {
+    let Some(code) = layer.cells.get(i * layer.height + j).map(|cell| cell.ch) else { return String::new(); };
+    if code == 0 {
+        return String::new();
+    }
+
+    let literal = CP437.get(code as usize).map(char::to_string).unwrap_or_default();
+    if let Some(font) = font {
+        let tag = format!("cp437={}", code);
+        if let Some(glyph) = font.glyphs.values().find(|glyph| glyph.has_tag(&tag)) {
+            return glyph.name.clone();
+        }
+
+        if font.glyphs.contains_key(&literal) {
+            return literal;
+        }
+    }
+
+    if is_horizontal(code) {
+        let corner = nearest_corner(layer, i, j, -1, 0, is_horizontal).or_else(|| nearest_corner(layer, i, j, 1, 0, is_horizontal));
+        return if corner.map(|corner| corner.starts_with("bottom")).unwrap_or(false) { "bottom" } else { "top" }.to_string();
+    }
+
+    if is_vertical(code) {
+        let corner = nearest_corner(layer, i, j, 0, -1, is_vertical).or_else(|| nearest_corner(layer, i, j, 0, 1, is_vertical));
+        return if corner.map(|corner| corner.ends_with("right")).unwrap_or(false) { "right" } else { "left" }.to_string();
+    }
+
+    frame_piece(code).map(str::to_string).unwrap_or(literal)
+}
+
+fn is_transparent(color: &XpColor) -> bool {
+    color.r == 255 && color.g == 0 && color.b == 255
+}
+
+fn to_color(color: &XpColor) -> Color {
+    Color::rgb_u8(color.r, color.g, color.b)
+}
+
+impl Grids {
+    /// Copies one layer of a REXPaint document into a grid, with its top-left corner at (x, y).
+    /// Cells with a transparent background are skipped so that layers can be stacked, and
+    /// characters are drawn with the matching glyphs of the grid's font, see `cp437_glyph`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn blit_xp(&mut self, tilesets: &Tilesets, fonts: &Fonts, grid: &str, x: i32, y: i32, doc: &RexpaintDocument, layer: usize) {
+        let Some(layer) = doc.0.layers.get(layer) else {
+            self.errors.push(SvarogError::MissingLayer { layer });
+            return;
+        };
+
+        let font = self.grids.get(grid)
+            .and_then(|grid| tilesets.tilesets.get(&grid.tileset))
+            .and_then(|tileset| fonts.fonts.get(&tileset.font));
+
+        for i in 0..layer.width {
+            for j in 0..layer.height {
+                // REXPaint stores its cells column by column
+                let Some(cell) = layer.cells.get(i * layer.height + j) else { continue; };
+                if is_transparent(&cell.bg) {
+                    continue;
+                }
+
+                self.set_colored(grid, x + i as i32, y + j as i32, &cp437_glyph(font, layer, i, j), to_color(&cell.fg), Some(to_color(&cell.bg)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod rex_testing {
+    use rexpaint::XpLayer;
+
+    use crate::loading::{Font, Glyph};
+
+    use super::cp437_glyph;
+
+    fn framed() -> XpLayer {
+        // ┌─┐
+        // │ │
+        // └─┘
+        let mut layer = XpLayer::new(3, 3);
+        let codes = [ [ 0xDA, 0xC4, 0xBF ], [ 0xB3, 0x20, 0xB3 ], [ 0xC0, 0xC4, 0xD9 ] ];
+        for (j, row) in codes.iter().enumerate() {
+            for (i, &code) in row.iter().enumerate() {
+                layer.cells[i * 3 + j].ch = code;
+            }
+        }
+        layer
+    }
+
+    #[test]
+    fn test_lines_face_their_corners_without_font() {
+        let layer = framed();
+        assert_eq!(cp437_glyph(None, &layer, 1, 0), "top");
+        assert_eq!(cp437_glyph(None, &layer, 1, 2), "bottom");
+        assert_eq!(cp437_glyph(None, &layer, 0, 1), "left");
+        assert_eq!(cp437_glyph(None, &layer, 2, 1), "right");
+        assert_eq!(cp437_glyph(None, &layer, 2, 2), "bottomright");
+    }
+
+    #[test]
+    fn test_font_glyphs_come_first() {
+        let layer = framed();
+        let mut font = Font::default();
+        font.glyphs.insert("hline".to_string(), Glyph { name: "hline".to_string(), x: 0, y: 0, attributes: vec![ "line, cp437=196".to_string() ] });
+        font.glyphs.insert("│".to_string(), Glyph { name: "│".to_string(), x: 1, y: 0, attributes: vec![] });
+
+        assert_eq!(cp437_glyph(Some(&font), &layer, 1, 2), "hline");
+        assert_eq!(cp437_glyph(Some(&font), &layer, 2, 1), "│");
+        assert_eq!(cp437_glyph(Some(&font), &layer, 0, 0), "topleft");
+    }
+}
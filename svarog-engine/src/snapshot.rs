@@ -0,0 +1,100 @@
+use bevy::render::color::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::{errors::SvarogError, loading::{strings, Cell, Fog, GridKind, Grids}};
+
+/// A cell with its glyph and tileset spelled out by name, so it survives a restart
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CellSnapshot {
+    pub glyph: String,
+    pub tileset: String,
+    pub foreground: Color,
+    pub background: Option<Color>,
+    #[serde(default)]
+    pub blink: bool,
+}
+
+/// The contents of a grid. Entity grids aren't snapshotted, as entities don't outlive the app.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GridSnapshot {
+    pub name: String,
+    pub width: i32,
+    pub height: i32,
+    /// Cells of glyph grids, in the same order as `Grid::cells`
+    #[serde(default)]
+    pub cells: Vec<CellSnapshot>,
+    #[serde(default)]
+    pub fog: Vec<Fog>,
+    /// Bits of boolean grids
+    #[serde(default)]
+    pub bits: Vec<u64>,
+}
+
+impl Grids {
+    pub fn snapshot(&self) -> Vec<GridSnapshot> {
+        let mut strings = strings().lock().unwrap();
+        let mut snapshots = self.grids.values()
+            .filter(|grid| grid.kind != GridKind::Entity)
+            .map(|grid| GridSnapshot {
+                name: grid.name.clone(),
+                width: grid.width,
+                height: grid.height,
+                cells: grid.cells.iter().map(|cell| CellSnapshot {
+                    glyph: if cell.is_empty() { String::new() } else { strings.out(cell.glyph).cloned().unwrap_or_default() },
+                    tileset: strings.out(cell.tileset).cloned().unwrap_or_else(|| grid.tileset.clone()),
+                    foreground: cell.foreground,
+                    background: cell.background,
+                    blink: cell.blink,
+                }).collect(),
+                fog: grid.fog.clone(),
+                bits: grid.bits.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        snapshots.sort_by(|a, b| a.name.cmp(&b.name));
+        snapshots
+    }
+
+    /// Puts snapshotted contents back into the grids with the same names, resizing them if needed.
+    /// Snapshots of grids that don't exist are skipped.
+    pub fn restore(&mut self, snapshots: &[GridSnapshot]) {
+        for snapshot in snapshots {
+            let Some(grid) = self.grids.get_mut(&snapshot.name) else {
+                self.errors.push(SvarogError::UnknownGrid { grid: snapshot.name.clone() });
+                continue;
+            };
+
+            if (grid.width, grid.height) != (snapshot.width, snapshot.height) {
+                grid.resize(snapshot.width, snapshot.height);
+            }
+
+            match grid.kind {
+                GridKind::Glyph => {
+                    let mut strings = strings().lock().unwrap();
+                    for (index, saved) in snapshot.cells.iter().enumerate().take(grid.cells.len()) {
+                        let (x, y) = grid.coords(index);
+                        let cell = Cell {
+                            tileset: strings.pass(&saved.tileset),
+                            glyph: if saved.glyph.is_empty() { 0 } else { strings.pass(&saved.glyph) },
+                            foreground: saved.foreground,
+                            background: saved.background,
+                            blink: saved.blink,
+                        };
+                        grid.put(x, y, cell);
+                    }
+
+                    for (index, fog) in snapshot.fog.iter().enumerate() {
+                        grid.set_fog(index, *fog);
+                    }
+                },
+                GridKind::Boolean => {
+                    if grid.bits.len() == snapshot.bits.len() {
+                        grid.bits = snapshot.bits.clone();
+                        grid.version += 1;
+                    }
+                },
+                GridKind::Entity => {},
+            }
+        }
+    }
+}
@@ -51,6 +51,8 @@ where
 {
     /// Handles to the Assets the were loaded from the rows of this CSV file
     pub rows: Vec<Handle<A>>,
+    /// Line of the file each of the rows starts on, so problems with a row can point back at it
+    pub lines: Vec<u64>,
 }
 
 impl<A> AssetLoader for TableAssetLoader<A>
@@ -73,14 +75,19 @@ where
             let mut reader = csv::ReaderBuilder::new()
                 .delimiter(b'|')
                 .comment(Some(b'#'))
+                .trim(csv::Trim::All)
                 .from_reader(bytes.as_slice());
+            let headers = reader.byte_headers()?.clone();
+            let mut record = csv::ByteRecord::new();
             let mut handles = vec![];
-            for (index, result) in reader.deserialize().enumerate() {
-                let asset: A = result?;
+            let mut lines = vec![];
+            while reader.read_byte_record(&mut record)? {
+                let asset: A = record.deserialize(Some(&headers))?;
+                lines.push(record.position().map(|position| position.line()).unwrap_or_default());
                 handles
-                    .push(load_context.add_loaded_labeled_asset(index.to_string(), asset.into()));
+                    .push(load_context.add_loaded_labeled_asset(handles.len().to_string(), asset.into()));
             }
-            Ok(LoadedCsv { rows: handles })
+            Ok(LoadedCsv { rows: handles, lines })
         })
     }
 
@@ -0,0 +1,423 @@
+use std::{iter::Peekable, str::Chars};
+
+use bevy::render::color::Color;
+
+use crate::loading::{strings, Grids, Strings, Word};
+
+/// A rectangle of cells, with `y` growing downwards like everywhere else in `Grids`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CellRect {
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+}
+
+impl CellRect {
+    pub fn new(x: i32, y: i32, w: i32, h: i32) -> Self {
+        Self { x, y, w, h }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextOptions {
+    pub align: TextAlign,
+    /// Break lines between words to fit the width; otherwise only newlines start a new line
+    pub wrap: bool,
+    /// Put at the end of text that had to be cut off, one glyph per character. Leave empty to just cut.
+    pub ellipsis: String,
+    pub foreground: Color,
+    pub background: Option<Color>,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        Self {
+            align: TextAlign::Left,
+            wrap: true,
+            ellipsis: "...".to_string(),
+            foreground: Color::WHITE,
+            background: None,
+        }
+    }
+}
+
+impl TextOptions {
+    pub fn aligned(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn colored(mut self, foreground: Color, background: Option<Color>) -> Self {
+        self.foreground = foreground;
+        self.background = background;
+        self
+    }
+}
+
+/// One cell's worth of printed text, with the styling the markup around it asked for
+#[derive(Debug, Clone, PartialEq)]
+pub struct Styled {
+    pub glyph: String,
+    /// Draws the glyph from this tileset instead of the grid's
+    pub tileset: Option<String>,
+    pub foreground: Color,
+    pub background: Option<Color>,
+    pub blink: bool,
+}
+
+impl Styled {
+    pub fn plain(glyph: &str, foreground: Color, background: Option<Color>) -> Self {
+        Self { glyph: glyph.to_string(), tileset: None, foreground, background, blink: false }
+    }
+
+    fn is(&self, glyph: &str) -> bool {
+        self.tileset.is_none() && self.glyph == glyph
+    }
+}
+
+/// Color names understood by markup, besides `#rrggbb`
+pub fn parse_color(name: &str) -> Option<Color> {
+    match name.trim().to_lowercase().as_str() {
+        "white" => Some(Color::WHITE),
+        "black" => Some(Color::BLACK),
+        "gray" | "grey" => Some(Color::GRAY),
+        "red" => Some(Color::RED),
+        "green" => Some(Color::GREEN),
+        "blue" => Some(Color::BLUE),
+        "yellow" => Some(Color::YELLOW),
+        "orange" => Some(Color::ORANGE),
+        "cyan" => Some(Color::CYAN),
+        "purple" | "magenta" => Some(Color::PURPLE),
+        "pink" => Some(Color::PINK),
+        name => name.strip_prefix('#').and_then(|hex| Color::hex(hex).ok()),
+    }
+}
+
+fn tag(name: &str) -> Option<Word> {
+    match name.trim() {
+        "/" => Some(Word::Pop),
+        "blink" => Some(Word::Blink),
+        name => match name.strip_prefix("bg:") {
+            Some(color) => parse_color(color).map(Word::Background),
+            None => parse_color(name.strip_prefix("fg:").unwrap_or(name)).map(Word::Foreground),
+        },
+    }
+}
+
+/// Reads up to the closing character, returning what was read and whether the closing character was found
+fn until(chars: &mut Peekable<Chars>, end: char) -> (String, bool) {
+    let mut read = String::new();
+    for c in chars.by_ref() {
+        if c == end {
+            return (read, true);
+        }
+        read.push(c);
+    }
+    (read, false)
+}
+
+fn flush(text: &mut String, words: &mut Vec<Word>, strings: &mut Strings) {
+    if !text.is_empty() {
+        words.push(Word::Text(strings.pass(text)));
+        text.clear();
+    }
+}
+
+/// Compiles print markup:
+/// - `/name/` inserts the glyph called `name`, and `/tileset:name/` takes it from another tileset
+/// - `{red}`, `{#ff8000}` or `{fg:red}` color the text up to the matching `{/}`
+/// - `{bg:blue}` gives the text a background, and `{blink}` makes it blink
+/// - `\` makes the next character literal, so `\{` prints a brace; `//` also prints a slash
+///
+/// Anything that doesn't parse as markup is printed as it is.
+pub fn compile(value: &str) -> Vec<Word> {
+    let mut strings = strings().lock().unwrap();
+    let mut words = vec![];
+    let mut text = String::new();
+    let mut chars = value.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(next) = chars.next() {
+                    text.push(next);
+                }
+            },
+            '/' => {
+                let (name, closed) = until(&mut chars, '/');
+                if !closed || name.is_empty() {
+                    text.push('/');
+                    text.push_str(&name);
+                    continue;
+                }
+
+                flush(&mut text, &mut words, &mut strings);
+                words.push(match name.split_once(':') {
+                    Some((tileset, glyph)) => Word::VarIn(strings.pass(tileset), strings.pass(glyph)),
+                    None => Word::Var(strings.pass(&name)),
+                });
+            },
+            '{' => {
+                let (name, closed) = until(&mut chars, '}');
+                match tag(&name).filter(|_| closed) {
+                    Some(word) => {
+                        flush(&mut text, &mut words, &mut strings);
+                        words.push(word);
+                    },
+                    None => {
+                        text.push('{');
+                        text.push_str(&name);
+                        if closed {
+                            text.push('}');
+                        }
+                    },
+                }
+            },
+            c => text.push(c),
+        }
+    }
+
+    flush(&mut text, &mut words, &mut strings);
+    words
+}
+
+/// Makes text print as it is, for text that comes from the player rather than from the game
+pub fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '/' | '{') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Resolves compiled markup into cells, starting from the given colors
+pub fn style(words: &[Word], foreground: Color, background: Option<Color>) -> Vec<Styled> {
+    let mut strings = strings().lock().unwrap();
+    let mut spans: Vec<Word> = vec![];
+    let mut styled = vec![];
+
+    for word in words {
+        let fg = spans.iter().rev().find_map(|w| if let Word::Foreground(c) = w { Some(*c) } else { None }).unwrap_or(foreground);
+        let bg = spans.iter().rev().find_map(|w| if let Word::Background(c) = w { Some(Some(*c)) } else { None }).unwrap_or(background);
+        let blink = spans.contains(&Word::Blink);
+        let cell = |glyph: &str, tileset: Option<String>| Styled { glyph: glyph.to_string(), tileset, foreground: fg, background: bg, blink };
+
+        match word {
+            Word::Text(text) => {
+                let text = strings.out(*text).cloned().unwrap_or_default();
+                styled.extend(text.chars().map(|c| cell(&c.to_string(), None)));
+            },
+            Word::Var(name) => styled.extend(strings.out(*name).map(|name| cell(name, None))),
+            Word::VarIn(tileset, name) => {
+                let tileset = strings.out(*tileset).cloned();
+                styled.extend(strings.out(*name).map(|name| cell(name, tileset)));
+            },
+            Word::Pop => { spans.pop(); },
+            span => spans.push(*span),
+        }
+    }
+
+    styled
+}
+
+/// Greedily breaks a line of glyphs into lines at most `width` long, breaking at spaces where possible
+/// and splitting words that can't fit on a line of their own.
+fn wrap_line(glyphs: &[Styled], width: usize) -> Vec<Vec<Styled>> {
+    let width = width.max(1);
+    let mut lines = vec![];
+    let mut line: Vec<Styled> = vec![];
+
+    for word in glyphs.split(|g| g.is(" ")).filter(|word| !word.is_empty()) {
+        if !line.is_empty() && line.len() + 1 + word.len() <= width {
+            // the space keeps the style of the word before it, so backgrounds run on between words
+            let space = Styled { glyph: " ".to_string(), ..line[line.len() - 1].clone() };
+            line.push(space);
+            line.extend_from_slice(word);
+            continue;
+        }
+
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+
+        let mut word = word;
+        while word.len() > width {
+            lines.push(word[..width].to_vec());
+            word = &word[width..];
+        }
+        line.extend_from_slice(word);
+    }
+
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// Lays glyphs out in lines no wider than `width` and no more than `height`, marking cut text with the ellipsis
+pub fn layout(glyphs: &[Styled], width: usize, height: usize, options: &TextOptions) -> Vec<Vec<Styled>> {
+    let ellipsis = options.ellipsis.chars()
+        .map(|c| Styled::plain(&c.to_string(), options.foreground, options.background))
+        .collect::<Vec<_>>();
+    let mut cut = false;
+    let mut lines = vec![];
+
+    for paragraph in glyphs.split(|g| g.is("\n")) {
+        if options.wrap {
+            lines.extend(wrap_line(paragraph, width));
+        } else {
+            cut |= paragraph.len() > width;
+            lines.push(paragraph.iter().take(width).cloned().collect());
+        }
+    }
+
+    if lines.len() > height {
+        lines.truncate(height);
+        cut = true;
+    }
+
+    if cut && width > 0 {
+        if let Some(last) = lines.last_mut() {
+            let keep = width.saturating_sub(ellipsis.len()).min(last.len());
+            last.truncate(keep);
+            while last.last().map(|g| g.is(" ")).unwrap_or(false) {
+                last.pop();
+            }
+            last.extend(ellipsis.iter().take(width).cloned());
+        }
+    }
+
+    lines
+}
+
+impl Grids {
+    /// Prints text inside a rectangle, wrapping, aligning and cutting it as the options say.
+    /// Returns how many lines were used.
+    pub fn print_box(&mut self, grid: &str, rect: CellRect, text: &str, options: &TextOptions) -> i32 {
+        if rect.w <= 0 || rect.h <= 0 {
+            return 0;
+        }
+
+        let glyphs = style(&self.words(text), options.foreground, options.background);
+        self.print_glyphs(grid, rect, &glyphs, options)
+    }
+
+    /// Lays out and prints glyphs that were already styled, for text that changes too often for `print_box`
+    /// to keep what it compiled. Returns how many lines were used.
+    pub fn print_glyphs(&mut self, grid: &str, rect: CellRect, glyphs: &[Styled], options: &TextOptions) -> i32 {
+        if rect.w <= 0 || rect.h <= 0 {
+            return 0;
+        }
+
+        let lines = layout(glyphs, rect.w as usize, rect.h as usize, options);
+
+        for (row, line) in lines.iter().enumerate() {
+            let offset = match options.align {
+                TextAlign::Left => 0,
+                TextAlign::Center => (rect.w - line.len() as i32) / 2,
+                TextAlign::Right => rect.w - line.len() as i32,
+            };
+
+            for (column, glyph) in line.iter().enumerate() {
+                let (x, y) = (rect.x + offset + column as i32, rect.y + row as i32);
+                if self.contains(grid, x, y) {
+                    self.set_styled(grid, x, y, glyph);
+                }
+            }
+        }
+
+        lines.len() as i32
+    }
+}
+
+#[cfg(test)]
+mod text_testing {
+    use bevy::render::color::Color;
+
+    use super::{compile, escape, layout, style, TextOptions};
+
+    fn lines(text: &str, width: usize, height: usize, options: &TextOptions) -> Vec<String> {
+        let glyphs = style(&compile(text), Color::WHITE, None);
+        layout(&glyphs, width, height, options).iter()
+            .map(|line| line.iter().map(|g| g.glyph.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_wraps_between_words() {
+        let options = TextOptions::default();
+        assert_eq!(lines("the quick brown fox", 10, 5, &options), vec![ "the quick", "brown fox" ]);
+    }
+
+    #[test]
+    fn test_splits_long_words() {
+        let options = TextOptions::default();
+        assert_eq!(lines("abcdefghij", 4, 5, &options), vec![ "abcd", "efgh", "ij" ]);
+    }
+
+    #[test]
+    fn test_keeps_newlines() {
+        let options = TextOptions::default();
+        assert_eq!(lines("one\ntwo", 10, 5, &options), vec![ "one", "two" ]);
+    }
+
+    #[test]
+    fn test_cut_text_gets_ellipsis() {
+        let options = TextOptions::default();
+        assert_eq!(lines("one two three four", 9, 1, &options), vec![ "one tw..." ]);
+
+        let options = TextOptions { wrap: false, ..Default::default() };
+        assert_eq!(lines("one two three", 8, 1, &options), vec![ "one t..." ]);
+    }
+
+    #[test]
+    fn test_handles_multibyte_characters() {
+        let options = TextOptions { ellipsis: "…".to_string(), ..Default::default() };
+        assert_eq!(lines("žuta čaša", 5, 1, &options), vec![ "žuta…" ]);
+    }
+
+    #[test]
+    fn test_color_spans_nest() {
+        let glyphs = style(&compile("a{red}b{bg:blue}c{/}d{/}e"), Color::WHITE, None);
+        let colors = glyphs.iter().map(|g| (g.glyph.as_str(), g.foreground, g.background)).collect::<Vec<_>>();
+        assert_eq!(colors, vec![
+            ("a", Color::WHITE, None),
+            ("b", Color::RED, None),
+            ("c", Color::RED, Some(Color::BLUE)),
+            ("d", Color::RED, None),
+            ("e", Color::WHITE, None),
+        ]);
+    }
+
+    #[test]
+    fn test_glyphs_and_tileset_override() {
+        let glyphs = style(&compile("/hero1/ and /oryx:sword/{blink}!{/}"), Color::WHITE, None);
+        assert_eq!(glyphs[0].glyph, "hero1");
+        assert_eq!(glyphs[0].tileset, None);
+        assert_eq!(glyphs[6].glyph, "sword");
+        assert_eq!(glyphs[6].tileset.as_deref(), Some("oryx"));
+        assert!(!glyphs[6].blink);
+        assert!(glyphs[7].blink);
+        assert_eq!(glyphs.len(), 8);
+    }
+
+    #[test]
+    fn test_escapes_and_unknown_tags_are_literal() {
+        let options = TextOptions::default();
+        assert_eq!(lines("\\{red} {nope} a//b 50%", 30, 1, &options), vec![ "{red} {nope} a/b 50%" ]);
+        assert_eq!(lines(&escape("{red}/x/ a\\b"), 30, 1, &options), vec![ "{red}/x/ a\\b" ]);
+    }
+}
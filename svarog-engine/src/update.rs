@@ -1,31 +1,261 @@
-use std::marker::PhantomData;
+use std::{collections::HashSet, marker::PhantomData};
 
-use bevy::{app::{Plugin, PostUpdate}, ecs::{entity::Entity, schedule::{common_conditions::in_state, IntoSystemConfigs}, 
-    system::{Commands, Query, Res, ResMut}}, render::view::Visibility, sprite::TextureAtlasSprite};
+use bevy::{app::{Plugin, PostUpdate}, asset::Handle, ecs::{query::{With, Without}, schedule::{common_conditions::in_state, IntoSystemConfigs},
+    system::{Commands, Local, Query, Res, ResMut}}, hierarchy::{BuildChildren, DespawnRecursiveExt}, math::{Vec2, Vec3}, render::{camera::OrthographicProjection, color::Color, view::{Visibility, VisibilityBundle}},
+    sprite::{Sprite, SpriteBundle, SpriteSheetBundle, TextureAtlas, TextureAtlasSprite}, time::Time, transform::{components::{GlobalTransform, Transform}, TransformBundle}, window::{PrimaryWindow, Window}};
 use bevy_rand::{plugin::EntropyPlugin, prelude::WyRand};
 
-use crate::loading::strings;
+use crate::{errors::{Reported, SvarogError}, loading::strings, windows::SvarogScale};
 
-use super::loading::{Fonts, SetGridValue, Strings, SvarogStates, Tilesets};
+use super::loading::{CameraTag, Cell, Fog, Fonts, Grid, GridAlign, GridChunk, GridKind, Grids, Strings, SvarogStates, Tileset, Tilesets, CHUNK_SIZE};
 
-pub fn grid_update_values(
+fn dim(color: Color, amount: f32) -> Color {
+    Color::rgba(color.r() * amount, color.g() * amount, color.b() * amount, color.a())
+}
+
+/// How the two sprites of a cell should look, worked out from the cell and its fog
+#[derive(Debug, Default)]
+pub struct CellLook {
+    /// Atlas index of the glyph, or `None` if it couldn't be found
+    pub index: Option<usize>,
+    /// Atlas of the cell's tileset, when it's known; otherwise the grid's atlas is used
+    pub atlas: Option<Handle<TextureAtlas>>,
+    pub foreground: Color,
+    pub background: Option<Color>,
+    pub shown: bool,
+}
+
+/// Seconds blinking cells spend shown, and then hidden
+pub const BLINK_RATE: f32 = 0.5;
+
+/// `blinked` says whether blinking cells are currently in their hidden phase
+#[allow(clippy::too_many_arguments)]
+pub fn cell_look(cell: &Cell, fog: Fog, remembered: f32, blinked: bool, strings: &mut Strings, tilesets: &Tilesets, fonts: &Fonts, reported: &mut Reported) -> CellLook {
+    let tint = |color: Color| if fog == Fog::Remembered { dim(color, remembered) } else { color };
+    let mut look = CellLook {
+        index: None,
+        atlas: None,
+        foreground: tint(cell.foreground),
+        background: cell.background.filter(|_| fog != Fog::Unseen).map(tint),
+        shown: !cell.is_empty() && fog != Fog::Unseen && !(cell.blink && blinked),
+    };
+
+    if !cell.is_empty() {
+        let Some(tileset) = strings.out(cell.tileset).and_then(|name| tilesets.tilesets.get(name)) else {
+            reported.report(SvarogError::UnknownTileset { at: None, tileset: strings.out(cell.tileset).cloned().unwrap_or_default() });
+            return look;
+        };
+        let Some(font) = fonts.fonts.get(&tileset.font) else {
+            reported.report(SvarogError::UnknownFont { tileset: tileset.name.clone(), font: tileset.font.clone() });
+            return look;
+        };
+        let Some(glyph) = strings.out(cell.glyph).and_then(|name| font.glyphs.get(name)) else {
+            reported.report(SvarogError::MissingGlyph { grid: None, tileset: tileset.name.clone(), glyph: strings.out(cell.glyph).cloned().unwrap_or_default() });
+            return look;
+        };
+        look.index = Some(((glyph.x - 1) + (glyph.y - 1) * tileset.columns) as usize);
+        look.atlas = tilesets.atlases.get(&tileset.name).cloned();
+    }
+
+    look
+}
+
+impl Grid {
+    /// Chunks of the grid that overlap the given world rectangle, plus a chunk of margin around them.
+    /// Without a rectangle, every chunk is returned.
+    pub fn chunks_in_view(&self, tileset: &Tileset, view: Option<(Vec2, Vec2)>) -> HashSet<(i32, i32)> {
+        let columns = (self.width + CHUNK_SIZE - 1) / CHUNK_SIZE;
+        let rows = (self.height + CHUNK_SIZE - 1) / CHUNK_SIZE;
+
+        let (x0, y0, x1, y1) = match view {
+            None => (0, 0, columns - 1, rows - 1),
+            Some((min, max)) => {
+                let (w, h) = (tileset.width as f32, tileset.height as f32);
+                let i0 = ((min.x + w * 0.5) / w).floor() as i32 - self.x;
+                let i1 = ((max.x + w * 0.5) / w).floor() as i32 - self.x;
+                let j0 = ((min.y + h * 0.5) / h).floor() as i32 - self.y;
+                let j1 = ((max.y + h * 0.5) / h).floor() as i32 - self.y;
+
+                // world rows grow upwards while grid rows grow downwards
+                let (x0, x1) = (i0.max(0), i1.min(self.width - 1));
+                let (y0, y1) = ((self.height - 1 - j1).max(0), (self.height - 1 - j0).min(self.height - 1));
+                if x0 > x1 || y0 > y1 {
+                    return HashSet::new();
+                }
+
+                ((x0 / CHUNK_SIZE - 1).max(0), (y0 / CHUNK_SIZE - 1).max(0), (x1 / CHUNK_SIZE + 1).min(columns - 1), (y1 / CHUNK_SIZE + 1).min(rows - 1))
+            },
+        };
+
+        (y0..=y1).flat_map(|cy| (x0..=x1).map(move |cx| (cx, cy))).collect()
+    }
+}
+
+/// Spawns the sprites for one chunk of a glyph grid under the grid entity, already showing the cells' contents
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_chunk(commands: &mut Commands, grid: &Grid, tileset: &Tileset, (cx, cy): (i32, i32), blinked: bool, strings: &mut Strings, tilesets: &Tilesets, fonts: &Fonts, reported: &mut Reported) -> Option<GridChunk> {
+    let parent = grid.entity?;
+    let atlas = grid.atlas.clone()?;
+    let size = (CHUNK_SIZE * CHUNK_SIZE) as usize;
+    let mut sprites = Vec::with_capacity(size);
+    let mut backgrounds = Vec::with_capacity(size);
+
+    let entity = commands
+        .spawn((TransformBundle::default(), VisibilityBundle::default()))
+        .with_children(|f| {
+            for ly in 0..CHUNK_SIZE {
+                for lx in 0..CHUNK_SIZE {
+                    let (x, y) = (cx * CHUNK_SIZE + lx, cy * CHUNK_SIZE + ly);
+                    let j = grid.height - 1 - y;
+                    let position = Vec3::new(
+                        ((grid.x + x) * tileset.width) as f32,
+                        ((if grid.align == GridAlign::None { grid.y } else { 0 } + j) * tileset.height) as f32,
+                        grid.depth as f32);
+
+                    // cells past the edge of the grid still get hidden sprites, to keep chunks the same shape
+                    let look = grid.index(x, y)
+                        .map(|index| cell_look(&grid.cells[index], grid.fog[index], grid.remembered, blinked, strings, tilesets, fonts, reported))
+                        .unwrap_or_default();
+
+                    let background = f.spawn(SpriteBundle {
+                        sprite: Sprite {
+                            color: look.background.unwrap_or_default(),
+                            custom_size: Some(Vec2::new(tileset.width as f32, tileset.height as f32)),
+                            ..Default::default()
+                        },
+                        transform: Transform::from_translation(position - Vec3::Z * 0.5),
+                        visibility: if look.background.is_some() { Visibility::Visible } else { Visibility::Hidden },
+                        ..Default::default()
+                    }).id();
+
+                    let sprite = f.spawn(SpriteSheetBundle {
+                        sprite: TextureAtlasSprite { index: look.index.unwrap_or(0), color: look.foreground, ..Default::default() },
+                        texture_atlas: look.atlas.unwrap_or_else(|| atlas.clone()),
+                        transform: Transform::from_translation(position),
+                        visibility: if look.shown { Visibility::Visible } else { Visibility::Hidden },
+                        ..Default::default()
+                    }).id();
+
+                    sprites.push(sprite);
+                    backgrounds.push(background);
+                }
+            }
+        })
+        .set_parent(parent)
+        .id();
+
+    Some(GridChunk { entity, sprites, backgrounds })
+}
+
+/// Spawns the chunks of every glyph grid that come into the camera's view and despawns the ones that leave it.
+/// Camera-aligned grids move with the camera, so all of their chunks stay spawned.
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+pub fn grid_stream_chunks(
     mut commands: Commands,
+    mut grids: ResMut<Grids>,
     tilesets: Res<Tilesets>,
     fonts: Res<Fonts>,
-    mut changed_sprite_query: Query<(Entity, &mut TextureAtlasSprite, &mut Visibility, &SetGridValue)>,
+    scale: Res<SvarogScale>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&GlobalTransform, &OrthographicProjection), With<CameraTag>>,
+    mut reported: ResMut<Reported>,
 ) {
-    for (entity, mut sprite, mut visibility, SetGridValue { tileset, value }) in &mut changed_sprite_query {
-        *visibility = if *value != 0 { Visibility::Visible } else { Visibility::Hidden };
-        
-        if *value != 0 {
-            let mut strings = strings().lock().unwrap();
-            let Some(tileset) = tilesets.tilesets.get(strings.out(*tileset).unwrap()) else { println!("NO TILESET {}", tileset); continue; };
-            let Some(font) = fonts.fonts.get(&tileset.font) else { println!("NO FONT {}", tileset.font); continue; };
-            let Some(glyph) = font.glyphs.get(strings.out(*value).unwrap()) else { println!("NO GLYPH {}", value); continue; };
-            let index = (glyph.x - 1) + (glyph.y - 1) * tileset.columns;
-            sprite.index = index as usize;
+    let view = match (window.get_single(), camera.get_single()) {
+        (Ok(window), Ok((transform, projection))) => {
+            let half = scale.view_of(window) * 0.5 * projection.scale;
+            let center = transform.translation().truncate();
+            Some((center - half, center + half))
+        },
+        _ => None,
+    };
+
+    let blinked = grids.blinked;
+    let mut strings = strings().lock().unwrap();
+    for grid in grids.grids.values_mut() {
+        if grid.kind != GridKind::Glyph || grid.entity.is_none() {
+            continue;
+        }
+
+        let Some(tileset) = tilesets.tilesets.get(&grid.tileset) else { continue; };
+        let wanted = grid.chunks_in_view(tileset, if grid.align == GridAlign::None { view } else { None });
+
+        let stale = grid.chunks.keys().filter(|key| !wanted.contains(*key)).copied().collect::<Vec<_>>();
+        for key in stale {
+            if let Some(chunk) = grid.chunks.remove(&key) {
+                commands.entity(chunk.entity).despawn_recursive();
+            }
+        }
+
+        for key in wanted {
+            if grid.chunks.contains_key(&key) {
+                continue;
+            }
+
+            if let Some(chunk) = spawn_chunk(&mut commands, grid, tileset, key, blinked, &mut strings, &tilesets, &fonts, &mut reported) {
+                grid.chunks.insert(key, chunk);
+            }
         }
-        commands.entity(entity).remove::<SetGridValue>();
+    }
+}
+
+/// Brings the sprites of changed cells up to date. Cells in chunks that aren't spawned are skipped,
+/// as their chunk reads the cells afresh when it does get spawned.
+pub fn grid_update_values(
+    mut grids: ResMut<Grids>,
+    tilesets: Res<Tilesets>,
+    fonts: Res<Fonts>,
+    mut glyph_query: Query<(&mut TextureAtlasSprite, &mut Handle<TextureAtlas>, &mut Visibility)>,
+    mut background_query: Query<(&mut Sprite, &mut Visibility), Without<TextureAtlasSprite>>,
+    mut reported: ResMut<Reported>,
+) {
+    let blinked = grids.blinked;
+    let mut strings = strings().lock().unwrap();
+    for grid in grids.grids.values_mut() {
+        if grid.dirty.is_empty() {
+            continue;
+        }
+
+        let dirty = std::mem::take(&mut grid.dirty);
+        for index in dirty {
+            let Some(cell) = grid.cells.get(index) else { continue; };
+            let (x, y) = grid.coords(index);
+            let Some((sprite_entity, background_entity)) = grid.sprite(x, y) else { continue; };
+
+            let fog = grid.fog.get(index).copied().unwrap_or_default();
+            let look = cell_look(cell, fog, grid.remembered, blinked, &mut strings, &tilesets, &fonts, &mut reported);
+
+            if let Ok((mut sprite, mut atlas, mut visibility)) = glyph_query.get_mut(sprite_entity) {
+                *visibility = if look.shown { Visibility::Visible } else { Visibility::Hidden };
+                sprite.color = look.foreground;
+                if let Some(index) = look.index {
+                    sprite.index = index;
+                }
+                if let Some(cell_atlas) = look.atlas.filter(|cell_atlas| *cell_atlas != *atlas) {
+                    *atlas = cell_atlas;
+                }
+            }
+
+            if let Ok((mut background_sprite, mut background_visibility)) = background_query.get_mut(background_entity) {
+                *background_visibility = if look.background.is_some() { Visibility::Visible } else { Visibility::Hidden };
+                if let Some(color) = look.background {
+                    background_sprite.color = color;
+                }
+            }
+        }
+    }
+}
+
+/// Flips the blink phase every `BLINK_RATE` seconds and redraws the blinking cells
+pub fn grid_blink(time: Res<Time>, mut elapsed: Local<f32>, mut grids: ResMut<Grids>) {
+    *elapsed += time.delta_seconds();
+    if *elapsed < BLINK_RATE {
+        return;
+    }
+
+    *elapsed %= BLINK_RATE;
+    grids.blinked = !grids.blinked;
+    for grid in grids.grids.values_mut() {
+        grid.dirty.extend(grid.blinking.iter().copied());
     }
 }
 
@@ -34,6 +264,70 @@ pub struct SvarogGridPlugin<S: SvarogStates>(PhantomData<S>);
 
 impl<S: SvarogStates> Plugin for SvarogGridPlugin<S> {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.add_systems(PostUpdate, grid_update_values.run_if(in_state(S::done_loading_state())));
+        app.init_resource::<SvarogScale>()
+            .init_resource::<Reported>()
+            .add_systems(PostUpdate, (grid_blink, grid_stream_chunks, grid_update_values)
+            .chain()
+            .run_if(in_state(S::done_loading_state())));
+    }
+}
+
+#[cfg(test)]
+mod update_testing {
+    use bevy::{app::{App, Update}, asset::Handle, ecs::{schedule::IntoSystemConfigs, world::World}, render::color::Color, sprite::TextureAtlas};
+
+    use crate::{errors::Reported, loading::{strings, Font, Fonts, Glyph, Grid, GridKind, Grids, Tileset, Tilesets}, text::Styled, windows::SvarogScale};
+
+    use super::{grid_stream_chunks, grid_update_values};
+
+    const ASCII: Handle<TextureAtlas> = Handle::weak_from_u128(1);
+    const ORYX: Handle<TextureAtlas> = Handle::weak_from_u128(2);
+
+    fn app() -> App {
+        let mut tilesets = Tilesets::default();
+        let mut fonts = Fonts::default();
+        for (name, glyph) in [ ("ascii", "a"), ("oryx", "sword") ] {
+            tilesets.tilesets.insert(name.to_string(), Tileset { name: name.to_string(), font: name.to_string(), weight: 1, width: 10, height: 10, columns: 16, rows: 16 });
+            let mut font = Font::default();
+            font.glyphs.insert(glyph.to_string(), Glyph { name: glyph.to_string(), x: 2, y: 1, attributes: vec![] });
+            fonts.fonts.insert(name.to_string(), font);
+        }
+        tilesets.atlases.insert("ascii".to_string(), ASCII);
+        tilesets.atlases.insert("oryx".to_string(), ORYX);
+
+        let mut app = App::new();
+        let parent = app.world.spawn_empty().id();
+        let mut grid = Grid { name: "map".to_string(), width: 2, height: 1, kind: GridKind::Glyph, tileset: "ascii".to_string(), entity: Some(parent), atlas: Some(ASCII), ..Default::default() };
+        grid.allocate();
+
+        let mut grids = Grids::default();
+        grids.grids.insert("map".to_string(), grid);
+        app.insert_resource(tilesets).insert_resource(fonts).insert_resource(grids).init_resource::<SvarogScale>().init_resource::<Reported>()
+            .add_systems(Update, (grid_stream_chunks, grid_update_values).chain());
+        app
+    }
+
+    fn atlas_at(world: &World, x: i32, y: i32) -> Handle<TextureAtlas> {
+        let (sprite, _) = world.resource::<Grids>().grids["map"].sprite(x, y).unwrap();
+        world.get::<Handle<TextureAtlas>>(sprite).unwrap().clone()
+    }
+
+    #[test]
+    fn test_override_cells_draw_from_their_tileset() {
+        let mut app = app();
+        {
+            let mut grids = app.world.resource_mut::<Grids>();
+            grids.set_styled("map", 0, 0, &Styled { tileset: Some("oryx".to_string()), ..Styled::plain("sword", Color::WHITE, None) });
+            grids.set_colored("map", 1, 0, "a", Color::WHITE, None);
+        }
+        app.update();
+        assert_eq!(atlas_at(&app.world, 0, 0), ORYX);
+        assert_eq!(atlas_at(&app.world, 1, 0), ASCII);
+
+        app.world.resource_mut::<Grids>().set_colored("map", 0, 0, "a", Color::WHITE, None);
+        app.update();
+        assert_eq!(atlas_at(&app.world, 0, 0), ASCII);
+        let ascii = strings().lock().unwrap().pass("ascii");
+        assert_eq!(app.world.resource::<Grids>().get("map", 0, 0).map(|cell| cell.tileset), Some(ascii));
     }
 }
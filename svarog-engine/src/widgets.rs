@@ -0,0 +1,695 @@
+use std::{collections::HashMap, marker::PhantomData};
+
+use bevy::{app::{Plugin, Update}, ecs::{component::Component, entity::Entity, event::{Event, EventReader, EventWriter}, query::Changed, removal_detection::RemovedComponents,
+    schedule::{common_conditions::in_state, IntoSystemConfigs}, system::{Local, Query, Res, ResMut, Resource}},
+    input::{keyboard::KeyCode, mouse::{MouseButton, MouseWheel}, Input}, render::color::Color, window::ReceivedCharacter};
+
+use crate::{actions::ActionEvent, loading::{Grids, SvarogStates}, picking::{grid_clicks, GridClick, GridCursor, GridHover}, text::{compile, escape, style, CellRect, TextAlign, TextOptions}};
+
+const FRAME: [&str; 9] = [ "topleft", "topright", "bottomleft", "bottomright", "top", "bottom", "left", "right", " " ];
+
+/// What a widget is, along with whatever state it keeps
+#[derive(Debug, Clone, PartialEq)]
+pub enum WidgetKind {
+    Label { text: String, align: TextAlign },
+    Button { text: String },
+    Checkbox { text: String, checked: bool },
+    /// Shows as many items as the widget has rows, scrolling to keep the selected one in view
+    List { items: Vec<String>, selected: usize, scroll: usize },
+    ProgressBar { value: f32, max: f32, text: String },
+    /// Text typed by the player, at most `max` characters of it
+    TextInput { text: String, max: usize },
+    /// Takes all input while it's up, until one of its buttons is picked or it's cancelled
+    Dialog { title: String, text: String, buttons: Vec<String>, selected: usize },
+}
+
+/// A widget laid out in a region of a grid. Widgets are drawn and given input by `SvarogWidgetPlugin`
+/// for as long as their entity lives, and report what the player did with them as `WidgetEvent`s.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Widget {
+    pub grid: String,
+    pub rect: CellRect,
+    pub kind: WidgetKind,
+    /// Widgets get focus in this order, and then top to bottom and left to right
+    pub order: i32,
+    /// Widgets on higher layers are drawn over and picked before those on lower ones
+    pub layer: i32,
+    pub enabled: bool,
+}
+
+/// Input a widget reacts to, with clicks and such relative to the widget's top left cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UiInput {
+    Up,
+    Down,
+    Left,
+    Right,
+    Confirm,
+    Cancel,
+    Char(char),
+    Backspace,
+    Click(i32, i32),
+    Scroll(i32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WidgetAction {
+    Pressed,
+    Toggled(bool),
+    /// The selection of a list moved
+    Selected(usize),
+    /// An item of a list was confirmed or clicked
+    Chosen(usize),
+    Edited(String),
+    Submitted(String),
+    /// A button of a dialog was picked
+    Answered(usize),
+    Cancelled,
+}
+
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct WidgetEvent {
+    pub entity: Entity,
+    pub action: WidgetAction,
+}
+
+/// The widget keyboard input goes to. `typing` is set while that's a text input, so gameplay can ignore keys meanwhile.
+/// Nothing has focus, and actions are left to gameplay, until a menu sets `entity`, a widget is clicked or a dialog
+/// comes up. Focus is dropped again when its widget goes away.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct UiFocus {
+    pub entity: Option<Entity>,
+    pub typing: bool,
+}
+
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct UiTheme {
+    pub foreground: Color,
+    pub background: Option<Color>,
+    pub focus_foreground: Color,
+    pub focus_background: Option<Color>,
+    /// Behind the selected item of a list that doesn't have focus
+    pub selected_background: Option<Color>,
+    /// Behind buttons, checkboxes and text inputs that don't have focus
+    pub field_background: Option<Color>,
+    pub disabled: Color,
+    pub bar: Color,
+    pub track: Color,
+}
+
+impl Default for UiTheme {
+    fn default() -> Self {
+        Self {
+            foreground: Color::WHITE,
+            background: Some(Color::BLACK),
+            focus_foreground: Color::BLACK,
+            focus_background: Some(Color::WHITE),
+            selected_background: Some(Color::DARK_GRAY),
+            field_background: Some(Color::rgb(0.15, 0.15, 0.2)),
+            disabled: Color::GRAY,
+            bar: Color::rgb(0.2, 0.6, 0.2),
+            track: Color::rgb(0.15, 0.15, 0.15),
+        }
+    }
+}
+
+impl Widget {
+    pub fn new(grid: &str, rect: CellRect, kind: WidgetKind) -> Self {
+        Self { grid: grid.to_string(), rect, kind, order: 0, layer: 0, enabled: true }
+    }
+
+    pub fn label(grid: &str, rect: CellRect, text: &str) -> Self {
+        Self::new(grid, rect, WidgetKind::Label { text: text.to_string(), align: TextAlign::Left })
+    }
+
+    pub fn button(grid: &str, rect: CellRect, text: &str) -> Self {
+        Self::new(grid, rect, WidgetKind::Button { text: text.to_string() })
+    }
+
+    pub fn checkbox(grid: &str, rect: CellRect, text: &str, checked: bool) -> Self {
+        Self::new(grid, rect, WidgetKind::Checkbox { text: text.to_string(), checked })
+    }
+
+    pub fn list(grid: &str, rect: CellRect, items: Vec<String>) -> Self {
+        Self::new(grid, rect, WidgetKind::List { items, selected: 0, scroll: 0 })
+    }
+
+    pub fn progress_bar(grid: &str, rect: CellRect, value: f32, max: f32) -> Self {
+        Self::new(grid, rect, WidgetKind::ProgressBar { value, max, text: String::new() })
+    }
+
+    pub fn text_input(grid: &str, rect: CellRect, max: usize) -> Self {
+        Self::new(grid, rect, WidgetKind::TextInput { text: String::new(), max })
+    }
+
+    /// A dialog goes on the layer above everything else, and should be despawned once it's answered
+    pub fn dialog(grid: &str, rect: CellRect, title: &str, text: &str, buttons: &[&str]) -> Self {
+        Self {
+            layer: 1,
+            ..Self::new(grid, rect, WidgetKind::Dialog {
+                title: title.to_string(),
+                text: text.to_string(),
+                buttons: buttons.iter().map(|button| button.to_string()).collect(),
+                selected: 0,
+            })
+        }
+    }
+
+    pub fn with_order(mut self, order: i32) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn with_layer(mut self, layer: i32) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    pub fn is_focusable(&self) -> bool {
+        self.enabled && !matches!(self.kind, WidgetKind::Label { .. } | WidgetKind::ProgressBar { .. })
+    }
+
+    pub fn is_dialog(&self) -> bool {
+        matches!(self.kind, WidgetKind::Dialog { .. })
+    }
+
+    pub fn contains(&self, grid: &str, x: i32, y: i32) -> bool {
+        self.grid == grid && x >= self.rect.x && y >= self.rect.y && x < self.rect.x + self.rect.w && y < self.rect.y + self.rect.h
+    }
+
+    /// Reacts to input, changing the widget's state and returning what the player did, if anything
+    pub fn handle(&mut self, input: UiInput) -> Option<WidgetAction> {
+        let rect = self.rect;
+        match &mut self.kind {
+            WidgetKind::Label { .. } | WidgetKind::ProgressBar { .. } => None,
+            WidgetKind::Button { .. } => match input {
+                UiInput::Confirm | UiInput::Click(_, _) => Some(WidgetAction::Pressed),
+                _ => None,
+            },
+            WidgetKind::Checkbox { checked, .. } => match input {
+                UiInput::Confirm | UiInput::Click(_, _) => {
+                    *checked = !*checked;
+                    Some(WidgetAction::Toggled(*checked))
+                },
+                _ => None,
+            },
+            WidgetKind::List { items, selected, scroll } => {
+                let rows = rect.h.max(1) as usize;
+                let action = match input {
+                    UiInput::Up if *selected > 0 => {
+                        *selected -= 1;
+                        Some(WidgetAction::Selected(*selected))
+                    },
+                    UiInput::Down if *selected + 1 < items.len() => {
+                        *selected += 1;
+                        Some(WidgetAction::Selected(*selected))
+                    },
+                    UiInput::Confirm if !items.is_empty() => Some(WidgetAction::Chosen(*selected)),
+                    UiInput::Click(_, y) if y >= 0 && *scroll + (y as usize) < items.len() => {
+                        *selected = *scroll + y as usize;
+                        Some(WidgetAction::Chosen(*selected))
+                    },
+                    UiInput::Scroll(delta) => {
+                        let last = items.len().saturating_sub(rows);
+                        *scroll = (*scroll as i32 + delta).clamp(0, last as i32) as usize;
+                        return None;
+                    },
+                    _ => None,
+                };
+
+                // keep the selected item in view
+                if *selected < *scroll {
+                    *scroll = *selected;
+                } else if *selected >= *scroll + rows {
+                    *scroll = *selected + 1 - rows;
+                }
+                action
+            },
+            WidgetKind::TextInput { text, max } => match input {
+                UiInput::Char(c) if text.chars().count() < *max => {
+                    text.push(c);
+                    Some(WidgetAction::Edited(text.clone()))
+                },
+                UiInput::Backspace if !text.is_empty() => {
+                    text.pop();
+                    Some(WidgetAction::Edited(text.clone()))
+                },
+                UiInput::Confirm => Some(WidgetAction::Submitted(text.clone())),
+                _ => None,
+            },
+            WidgetKind::Dialog { buttons, selected, .. } => match input {
+                UiInput::Left | UiInput::Up if *selected > 0 => {
+                    *selected -= 1;
+                    None
+                },
+                UiInput::Right | UiInput::Down if *selected + 1 < buttons.len() => {
+                    *selected += 1;
+                    None
+                },
+                UiInput::Confirm if !buttons.is_empty() => Some(WidgetAction::Answered(*selected)),
+                UiInput::Cancel => Some(WidgetAction::Cancelled),
+                UiInput::Click(x, y) if y == rect.h - 2 => {
+                    let clicked = dialog_buttons(rect.w, buttons).iter().position(|&(start, width)| x >= start && x < start + width)?;
+                    *selected = clicked;
+                    Some(WidgetAction::Answered(clicked))
+                },
+                _ => None,
+            },
+        }
+    }
+
+    pub fn draw(&self, grids: &mut Grids, focused: bool, theme: &UiTheme) {
+        let (grid, rect) = (self.grid.as_str(), self.rect);
+        let foreground = if self.enabled { theme.foreground } else { theme.disabled };
+        let (field_foreground, field_background) = if focused {
+            (theme.focus_foreground, theme.focus_background)
+        } else {
+            (foreground, theme.field_background)
+        };
+
+        match &self.kind {
+            WidgetKind::Label { text, align } => {
+                fill(grids, grid, rect, theme.background);
+                grids.print_box(grid, rect, text, &TextOptions::default().aligned(*align).colored(foreground, theme.background));
+            },
+            WidgetKind::Button { text } => {
+                fill(grids, grid, rect, field_background);
+                let row = CellRect::new(rect.x, rect.y + (rect.h - 1) / 2, rect.w, 1);
+                grids.print_box(grid, row, text, &line().aligned(TextAlign::Center).colored(field_foreground, field_background));
+            },
+            WidgetKind::Checkbox { text, checked } => {
+                fill(grids, grid, rect, field_background);
+                let text = format!("[{}] {}", if *checked { "x" } else { " " }, text);
+                grids.print_box(grid, rect, &text, &line().colored(field_foreground, field_background));
+            },
+            WidgetKind::List { items, selected, scroll } => {
+                fill(grids, grid, rect, theme.background);
+                for (row, (index, item)) in items.iter().enumerate().skip(*scroll).take(rect.h.max(0) as usize).enumerate() {
+                    let (item_foreground, item_background) = match (index == *selected, focused) {
+                        (true, true) => (theme.focus_foreground, theme.focus_background),
+                        (true, false) => (foreground, theme.selected_background),
+                        _ => (foreground, theme.background),
+                    };
+
+                    let line_rect = CellRect::new(rect.x, rect.y + row as i32, rect.w, 1);
+                    fill(grids, grid, line_rect, item_background);
+                    grids.print_box(grid, line_rect, item, &line().colored(item_foreground, item_background));
+                }
+
+                let corner = CellRect::new(rect.x + rect.w - 1, rect.y, 1, 1);
+                if *scroll > 0 {
+                    grids.print_box(grid, corner, "^", &line().colored(foreground, theme.background));
+                }
+                if *scroll + (rect.h.max(0) as usize) < items.len() {
+                    grids.print_box(grid, CellRect { y: rect.y + rect.h - 1, ..corner }, "v", &line().colored(foreground, theme.background));
+                }
+            },
+            WidgetKind::ProgressBar { value, max, text } => {
+                let filled = if *max > 0.0 { (rect.w as f32 * (value / max).clamp(0.0, 1.0)).round() as i32 } else { 0 };
+                fill(grids, grid, rect, None);
+                print_changing(grids, grid, CellRect::new(rect.x, rect.y + (rect.h - 1) / 2, rect.w, 1), text, &line().aligned(TextAlign::Center).colored(foreground, None));
+
+                // the text goes on top of the bar, so the bar is drawn as backgrounds after it
+                for (x, y) in cells(rect) {
+                    if grids.contains(grid, x, y) {
+                        grids.set_background(grid, x, y, Some(if x - rect.x < filled { theme.bar } else { theme.track }));
+                    }
+                }
+            },
+            WidgetKind::TextInput { text, .. } => {
+                fill(grids, grid, rect, field_background);
+                let shown = text.chars().count().saturating_sub((rect.w - 1).max(0) as usize);
+                let visible = escape(&text.chars().skip(shown).collect::<String>());
+                let caret = if focused { "{blink}_{/}" } else { "" };
+                print_changing(grids, grid, rect, &format!("{}{}", visible, caret), &line().colored(field_foreground, field_background));
+            },
+            WidgetKind::Dialog { title, text, buttons, selected } => {
+                grids.boxed(grid, rect.x, rect.y, rect.w - 1, rect.h - 1, &FRAME);
+                fill(grids, grid, CellRect::new(rect.x + 1, rect.y + 1, rect.w - 2, rect.h - 2), theme.background);
+                if !title.is_empty() {
+                    grids.print_box(grid, CellRect::new(rect.x + 2, rect.y, rect.w - 4, 1), &format!(" {} ", title), &line().colored(foreground, theme.background));
+                }
+                grids.print_box(grid, CellRect::new(rect.x + 2, rect.y + 1, rect.w - 4, rect.h - 4), text, &TextOptions::default().colored(foreground, theme.background));
+
+                for (index, (start, width)) in dialog_buttons(rect.w, buttons).into_iter().enumerate() {
+                    let (button_foreground, button_background) = if index == *selected && focused {
+                        (theme.focus_foreground, theme.focus_background)
+                    } else {
+                        (foreground, theme.field_background)
+                    };
+
+                    let button = CellRect::new(rect.x + start, rect.y + rect.h - 2, width, 1);
+                    grids.print_box(grid, button, &format!("[ {} ]", buttons[index]), &line().colored(button_foreground, button_background));
+                }
+            },
+        }
+    }
+}
+
+fn line() -> TextOptions {
+    TextOptions { wrap: false, ..Default::default() }
+}
+
+/// Prints text that changes as the player plays, like typed text or progress, without `Grids` keeping
+/// every version of it compiled
+fn print_changing(grids: &mut Grids, grid: &str, rect: CellRect, text: &str, options: &TextOptions) {
+    grids.print_glyphs(grid, rect, &style(&compile(text), options.foreground, options.background), options);
+}
+
+fn cells(rect: CellRect) -> impl Iterator<Item = (i32, i32)> {
+    (rect.y..rect.y + rect.h).flat_map(move |y| (rect.x..rect.x + rect.w).map(move |x| (x, y)))
+}
+
+/// Empties a region, leaving only the background
+fn fill(grids: &mut Grids, grid: &str, rect: CellRect, background: Option<Color>) {
+    for (x, y) in cells(rect) {
+        if grids.contains(grid, x, y) {
+            grids.set_colored(grid, x, y, "", Color::WHITE, background);
+        }
+    }
+}
+
+/// Where the buttons of a dialog `width` cells wide go on its button row, as starting columns and widths
+pub fn dialog_buttons(width: i32, buttons: &[String]) -> Vec<(i32, i32)> {
+    let widths = buttons.iter().map(|button| button.chars().count() as i32 + 4).collect::<Vec<_>>();
+    let total = widths.iter().sum::<i32>() + (widths.len() as i32 - 1).max(0);
+    let mut start = ((width - total) / 2).max(1);
+
+    widths.into_iter().map(|w| {
+        let button = (start, w);
+        start += w + 1;
+        button
+    }).collect()
+}
+
+/// The widgets focus moves between, in order. While a dialog is up, that's only the topmost dialog.
+pub fn focus_order(widgets: &[(Entity, &Widget)]) -> Vec<Entity> {
+    let dialog = widgets.iter()
+        .filter(|(_, widget)| widget.enabled && widget.is_dialog())
+        .max_by_key(|(entity, widget)| (widget.layer, *entity));
+    if let Some((entity, _)) = dialog {
+        return vec![ *entity ];
+    }
+
+    let mut focusable = widgets.iter().filter(|(_, widget)| widget.is_focusable()).collect::<Vec<_>>();
+    focusable.sort_by_key(|(entity, widget)| (widget.order, widget.rect.y, widget.rect.x, *entity));
+    focusable.into_iter().map(|(entity, _)| *entity).collect()
+}
+
+fn step(order: &[Entity], current: Option<Entity>, delta: i32) -> Option<Entity> {
+    let len = order.len() as i32;
+    if len == 0 {
+        return None;
+    }
+
+    let position = current.and_then(|current| order.iter().position(|entity| *entity == current))
+        .map(|position| position as i32)
+        .unwrap_or(if delta > 0 { -1 } else { 0 });
+    Some(order[(position + delta).rem_euclid(len) as usize])
+}
+
+/// Moves focus around and hands keyboard, mouse and typed input to the widgets it's meant for
+#[allow(clippy::too_many_arguments)]
+pub fn widget_input(
+    mut actions: EventReader<ActionEvent>,
+    mut typed: EventReader<ReceivedCharacter>,
+    keys: Res<Input<KeyCode>>,
+    mut hovers: EventReader<GridHover>,
+    mut clicks: EventReader<GridClick>,
+    mut wheel: EventReader<MouseWheel>,
+    cursor: Res<GridCursor>,
+    mut focus: ResMut<UiFocus>,
+    mut widgets: Query<(Entity, &mut Widget)>,
+    mut events: EventWriter<WidgetEvent>,
+) {
+    let all = widgets.iter().collect::<Vec<_>>();
+    let order = focus_order(&all);
+    let kind_of = |entity: Option<Entity>| entity.and_then(|entity| all.iter().find(|(e, _)| *e == entity)).map(|(_, widget)| &widget.kind);
+    let widget_at = |grid: &str, x: i32, y: i32| all.iter()
+        .filter(|(entity, widget)| order.contains(entity) && widget.contains(grid, x, y))
+        .max_by_key(|(entity, widget)| (widget.layer, *entity))
+        .map(|(entity, widget)| (*entity, widget.rect));
+
+    // a dialog takes focus on its own, as it takes all input while it's up
+    let dialog = order.first().copied().filter(|entity| all.iter().any(|(e, widget)| e == entity && widget.is_dialog()));
+    let mut current = focus.entity.filter(|entity| order.contains(entity)).or(dialog);
+    let mut inputs = Vec::new();
+
+    for event in actions.read() {
+        let Some(focused) = current else { continue; };
+        let typing = matches!(kind_of(current), Some(WidgetKind::TextInput { .. }));
+        let is_list = matches!(kind_of(current), Some(WidgetKind::List { .. }));
+
+        match event.action.as_str() {
+            "menu_next" => current = step(&order, current, 1),
+            "menu_prev" => current = step(&order, current, -1),
+            "menu_confirm" => inputs.push((focused, UiInput::Confirm)),
+            "menu_cancel" => inputs.push((focused, UiInput::Cancel)),
+            _ if typing => {},
+            "move_n" if is_list => inputs.push((focused, UiInput::Up)),
+            "move_s" if is_list => inputs.push((focused, UiInput::Down)),
+            "move_n" if order.len() > 1 => current = step(&order, current, -1),
+            "move_s" if order.len() > 1 => current = step(&order, current, 1),
+            "move_n" => inputs.push((focused, UiInput::Up)),
+            "move_s" => inputs.push((focused, UiInput::Down)),
+            "move_w" => inputs.push((focused, UiInput::Left)),
+            "move_e" => inputs.push((focused, UiInput::Right)),
+            _ => {},
+        }
+    }
+
+    let typing = matches!(kind_of(current), Some(WidgetKind::TextInput { .. }));
+    let typing_into = current.filter(|_| typing);
+    for event in typed.read() {
+        if let Some(focused) = typing_into.filter(|_| !event.char.is_control()) {
+            inputs.push((focused, UiInput::Char(event.char)));
+        }
+    }
+
+    if let Some(focused) = typing_into.filter(|_| keys.just_pressed(KeyCode::Back)) {
+        inputs.push((focused, UiInput::Backspace));
+    }
+
+    // hovering moves focus that's already among the widgets, but doesn't take it from gameplay or from typing
+    let hover_moves_focus = current.is_some() && !typing;
+    for hover in hovers.read().filter(|_| hover_moves_focus) {
+        if let Some((entity, _)) = hover.cell.and_then(|(x, y)| widget_at(&hover.grid, x, y)) {
+            current = Some(entity);
+        }
+    }
+
+    for click in clicks.read().filter(|click| click.button == MouseButton::Left) {
+        if let Some((entity, rect)) = widget_at(&click.grid, click.x, click.y) {
+            current = Some(entity);
+            inputs.push((entity, UiInput::Click(click.x - rect.x, click.y - rect.y)));
+        }
+    }
+
+    for scrolled in wheel.read().filter(|scrolled| scrolled.y != 0.0) {
+        let under = cursor.picks.iter().find_map(|pick| widget_at(&pick.grid, pick.x, pick.y));
+        if let Some((entity, _)) = under {
+            inputs.push((entity, UiInput::Scroll(if scrolled.y > 0.0 { -1 } else { 1 })));
+        }
+    }
+
+    let typing = matches!(kind_of(current), Some(WidgetKind::TextInput { .. }));
+    focus.set_if_neq(UiFocus { entity: current, typing });
+
+    for (entity, input) in inputs {
+        let Ok((_, mut widget)) = widgets.get_mut(entity) else { continue; };
+        if let Some(action) = widget.handle(input) {
+            events.send(WidgetEvent { entity, action });
+        }
+    }
+}
+
+/// Redraws all widgets when any of them changes, clearing the regions of those that moved or went away
+pub fn draw_widgets(
+    mut grids: ResMut<Grids>,
+    theme: Res<UiTheme>,
+    focus: Res<UiFocus>,
+    widgets: Query<(Entity, &Widget)>,
+    changed: Query<(), Changed<Widget>>,
+    mut removed: RemovedComponents<Widget>,
+    mut drawn: Local<HashMap<Entity, (String, CellRect)>>,
+) {
+    let removed = removed.read().collect::<Vec<_>>();
+    if removed.is_empty() && changed.is_empty() && !focus.is_changed() && !theme.is_changed() {
+        return;
+    }
+
+    let mut stale = removed.iter().filter_map(|entity| drawn.remove(entity)).collect::<Vec<_>>();
+    for (entity, widget) in &widgets {
+        if let Some((grid, rect)) = drawn.get(&entity).filter(|(grid, rect)| *grid != widget.grid || *rect != widget.rect) {
+            stale.push((grid.clone(), *rect));
+        }
+    }
+
+    for (grid, rect) in stale {
+        fill(&mut grids, &grid, rect, None);
+    }
+
+    let mut sorted = widgets.iter().collect::<Vec<_>>();
+    sorted.sort_by_key(|(entity, widget)| (widget.layer, *entity));
+    for (entity, widget) in sorted {
+        widget.draw(&mut grids, focus.entity == Some(entity), &theme);
+        drawn.insert(entity, (widget.grid.clone(), widget.rect));
+    }
+}
+
+/// Draws `Widget`s and drives them with actions, typed text and the mouse
+#[derive(Default)]
+pub struct SvarogWidgetPlugin<S: SvarogStates>(PhantomData<S>);
+
+impl<S: SvarogStates> Plugin for SvarogWidgetPlugin<S> {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_resource::<UiFocus>()
+            .init_resource::<UiTheme>()
+            .add_event::<WidgetEvent>()
+            .add_systems(Update, (widget_input, draw_widgets)
+                .chain()
+                .after(grid_clicks)
+                .run_if(in_state(S::done_loading_state())));
+    }
+}
+
+#[cfg(test)]
+mod widgets_testing {
+    use bevy::{app::{App, Update}, ecs::{entity::Entity, event::Events}, input::{keyboard::KeyCode, mouse::MouseWheel, Input}, window::ReceivedCharacter};
+
+    use crate::{actions::ActionEvent, picking::{GridClick, GridCursor, GridHover}, text::CellRect};
+
+    use super::{dialog_buttons, focus_order, widget_input, UiFocus, UiInput, Widget, WidgetAction, WidgetEvent, WidgetKind};
+
+    #[test]
+    fn test_list_scrolls_to_selection() {
+        let items = (0..10).map(|i| format!("item {}", i)).collect::<Vec<_>>();
+        let mut list = Widget::list("ui", CellRect::new(0, 0, 10, 3), items);
+
+        for _ in 0..4 {
+            list.handle(UiInput::Down);
+        }
+        assert_eq!(list.kind, WidgetKind::List { items: list_items(&list), selected: 4, scroll: 2 });
+
+        list.handle(UiInput::Scroll(10));
+        assert!(matches!(list.kind, WidgetKind::List { scroll: 7, .. }));
+
+        assert_eq!(list.handle(UiInput::Click(1, 1)), Some(WidgetAction::Chosen(8)));
+        assert_eq!(list.handle(UiInput::Click(1, 5)), None);
+        assert_eq!(list.handle(UiInput::Up), Some(WidgetAction::Selected(7)));
+        assert_eq!(list.handle(UiInput::Confirm), Some(WidgetAction::Chosen(7)));
+    }
+
+    fn list_items(widget: &Widget) -> Vec<String> {
+        match &widget.kind {
+            WidgetKind::List { items, .. } => items.clone(),
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn test_text_input_edits_up_to_max() {
+        let mut input = Widget::text_input("ui", CellRect::new(0, 0, 10, 1), 3);
+        for c in "abcd".chars() {
+            input.handle(UiInput::Char(c));
+        }
+        assert_eq!(input.handle(UiInput::Backspace), Some(WidgetAction::Edited("ab".to_string())));
+        assert_eq!(input.handle(UiInput::Confirm), Some(WidgetAction::Submitted("ab".to_string())));
+    }
+
+    #[test]
+    fn test_checkbox_toggles() {
+        let mut checkbox = Widget::checkbox("ui", CellRect::new(0, 0, 10, 1), "Sound", false);
+        assert_eq!(checkbox.handle(UiInput::Confirm), Some(WidgetAction::Toggled(true)));
+        assert_eq!(checkbox.handle(UiInput::Click(0, 0)), Some(WidgetAction::Toggled(false)));
+    }
+
+    #[test]
+    fn test_dialog_buttons() {
+
+        // 0123456789012345678
+        // ..[ Yes ] [ No ]...
+
+        let mut dialog = Widget::dialog("ui", CellRect::new(0, 0, 19, 6), "Quit", "Really?", &[ "Yes", "No" ]);
+        let WidgetKind::Dialog { buttons, .. } = &dialog.kind else { unreachable!() };
+        assert_eq!(dialog_buttons(19, buttons), vec![ (2, 7), (10, 6) ]);
+
+        assert_eq!(dialog.handle(UiInput::Right), None);
+        assert_eq!(dialog.handle(UiInput::Confirm), Some(WidgetAction::Answered(1)));
+        assert_eq!(dialog.handle(UiInput::Click(3, 4)), Some(WidgetAction::Answered(0)));
+        assert_eq!(dialog.handle(UiInput::Click(9, 4)), None);
+        assert_eq!(dialog.handle(UiInput::Cancel), Some(WidgetAction::Cancelled));
+    }
+
+    #[test]
+    fn test_focus_order_and_modal_dialogs() {
+        let (a, b, c, d) = (Entity::from_raw(1), Entity::from_raw(2), Entity::from_raw(3), Entity::from_raw(4));
+        let lower = Widget::button("ui", CellRect::new(0, 5, 5, 1), "Lower");
+        let upper = Widget::button("ui", CellRect::new(0, 1, 5, 1), "Upper");
+        let label = Widget::label("ui", CellRect::new(0, 0, 5, 1), "Title");
+        let dialog = Widget::dialog("ui", CellRect::new(0, 0, 20, 6), "Quit", "Really?", &[ "Yes", "No" ]);
+
+        assert_eq!(focus_order(&[ (a, &lower), (b, &upper), (c, &label) ]), vec![ b, a ]);
+        assert_eq!(focus_order(&[ (a, &lower), (b, &upper), (c, &label), (d, &dialog) ]), vec![ d ]);
+    }
+
+    fn input_app() -> App {
+        let mut app = App::new();
+        app.init_resource::<UiFocus>().init_resource::<GridCursor>().init_resource::<Input<KeyCode>>()
+            .add_event::<ActionEvent>().add_event::<ReceivedCharacter>().add_event::<GridHover>().add_event::<GridClick>()
+            .add_event::<MouseWheel>().add_event::<WidgetEvent>()
+            .add_systems(Update, widget_input);
+        app
+    }
+
+    fn act(app: &mut App, action: &str) {
+        app.world.send_event(ActionEvent { action: action.to_string(), repeat: false });
+        app.update();
+    }
+
+    #[test]
+    fn test_widgets_leave_actions_alone_until_focused() {
+        let mut app = input_app();
+        let list = app.world.spawn(Widget::list("ui", CellRect::new(0, 0, 10, 3), vec![ "a".to_string(), "b".to_string() ])).id();
+        app.world.spawn(Widget::button("ui", CellRect::new(0, 4, 10, 1), "Ok"));
+
+        act(&mut app, "move_s");
+        act(&mut app, "menu_confirm");
+        assert_eq!(app.world.resource::<UiFocus>().entity, None);
+        assert!(app.world.resource::<Events<WidgetEvent>>().is_empty());
+
+        app.world.resource_mut::<UiFocus>().entity = Some(list);
+        act(&mut app, "move_s");
+        assert!(matches!(app.world.get::<Widget>(list).unwrap().kind, WidgetKind::List { selected: 1, .. }));
+
+        let dialog = app.world.spawn(Widget::dialog("ui", CellRect::new(0, 0, 20, 6), "Quit", "Really?", &[ "Yes" ])).id();
+        app.world.despawn(list);
+        app.update();
+        assert_eq!(app.world.resource::<UiFocus>().entity, Some(dialog));
+
+        app.world.despawn(dialog);
+        app.update();
+        assert_eq!(app.world.resource::<UiFocus>().entity, None);
+    }
+
+    #[test]
+    fn test_hover_keeps_focus_on_text_input() {
+        let mut app = input_app();
+        let input = app.world.spawn(Widget::text_input("ui", CellRect::new(0, 0, 10, 1), 8)).id();
+        let button = app.world.spawn(Widget::button("ui", CellRect::new(0, 2, 10, 1), "Ok")).id();
+
+        app.world.send_event(GridHover { grid: "ui".to_string(), cell: Some((1, 2)) });
+        app.update();
+        assert_eq!(app.world.resource::<UiFocus>().entity, None);
+
+        app.world.resource_mut::<UiFocus>().entity = Some(input);
+        app.world.send_event(GridHover { grid: "ui".to_string(), cell: Some((1, 2)) });
+        app.update();
+        assert_eq!(*app.world.resource::<UiFocus>(), UiFocus { entity: Some(input), typing: true });
+
+        app.world.resource_mut::<UiFocus>().entity = Some(button);
+        app.world.send_event(GridHover { grid: "ui".to_string(), cell: Some((1, 0)) });
+        app.update();
+        assert_eq!(*app.world.resource::<UiFocus>(), UiFocus { entity: Some(input), typing: true });
+    }
+}
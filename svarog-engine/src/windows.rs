@@ -1,45 +1,133 @@
-use std::fs::File;
-
 use bevy::prelude::*;
 
+use bevy::audio::GlobalVolume;
+use bevy::render::camera::{ScalingMode, Viewport};
 use bevy::window::PrimaryWindow;
 use bevy::window::WindowMode;
 use bevy::window::WindowResolution;
 
-#[derive(serde::Deserialize)]
-struct Config {
-    pub title: String,
-    pub mode: SvarogWindowMode,
+use crate::config::{apply_config, config_path, PixelScaling, SvarogConfig};
+use crate::loading::{CameraTag, Tilesets};
+use crate::errors::report_on;
+pub use crate::config::SvarogWindowMode;
+
+#[derive(Resource)]
+pub struct SvarogWindowSize(pub u32, pub u32);
+
+/// The whole number everything is drawn scaled by, and the part of the window it's drawn in
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct SvarogScale {
+    pub scale: u32,
+    /// Size of the drawn area in world units, which is what camera-aligned grids line up with
+    pub view: Vec2,
+    /// Top left corner and size of the drawn area in physical pixels, when it doesn't take up the whole window
+    pub letterbox: Option<(UVec2, UVec2)>,
 }
 
-#[derive(PartialEq, Eq, serde::Deserialize)]
-pub enum SvarogWindowMode {
-    Fullscreen,
-    Windowed(u32, u32),
+impl Default for SvarogScale {
+    fn default() -> Self {
+        Self { scale: 1, view: Vec2::ZERO, letterbox: None }
+    }
 }
 
-#[derive(Resource)]
-pub struct SvarogWindowSize(pub u32, pub u32);
+impl SvarogScale {
+    /// Picks the largest whole number of physical pixels per tileset pixel at which `target` fits into the window,
+    /// and centers it. Without a target, the whole window is used and drawn the way Bevy does by default.
+    pub fn fit(target: Option<Vec2>, physical_window: Vec2, scale_factor: f32) -> Self {
+        let Some(target) = target.filter(|target| target.x > 0.0 && target.y > 0.0) else {
+            return Self { scale: 1, view: physical_window / scale_factor, letterbox: None };
+        };
+
+        let scale = ((physical_window.x / target.x).min(physical_window.y / target.y).floor() as u32).max(1);
+        let size = (target * scale as f32).min(physical_window);
+        let position = ((physical_window - size) * 0.5).floor().max(Vec2::ZERO);
+
+        Self {
+            scale,
+            view: size / scale as f32,
+            letterbox: Some((position.as_uvec2(), size.as_uvec2())),
+        }
+    }
+
+    /// How the camera has to scale the world for one of its units to take up `scale` physical pixels. Bevy sizes
+    /// projections in logical pixels, so that's divided by the window's scale factor. Without a target to fit,
+    /// a unit stays one logical pixel.
+    pub fn scaling_mode(&self, scale_factor: f32) -> ScalingMode {
+        if self.letterbox.is_some() {
+            ScalingMode::WindowSize(self.scale as f32 / scale_factor)
+        } else {
+            ScalingMode::WindowSize(self.scale as f32)
+        }
+    }
+
+    /// Size of the drawn area in world units; before the scale is first worked out, that's the size of the window
+    pub fn view_of(&self, window: &Window) -> Vec2 {
+        if self.view == Vec2::ZERO { Vec2::new(window.width(), window.height()) } else { self.view }
+    }
+}
+
+/// Size in logical pixels of the cells `PixelScaling` asks for
+fn scaling_target(scaling: &PixelScaling, default_tileset: &str, tilesets: &Tilesets) -> Option<Vec2> {
+    let name = if scaling.tileset.is_empty() { default_tileset } else { scaling.tileset.as_str() };
+    let tileset = tilesets.tilesets.get(name)?;
+    Some(Vec2::new((scaling.columns as i32 * tileset.width) as f32, (scaling.rows as i32 * tileset.height) as f32))
+}
+
+/// Works out `SvarogScale` for the current window and config, and sets the camera up to draw at it
+pub fn update_scale(
+    config: Res<SvarogConfig>,
+    tilesets: Option<Res<Tilesets>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    mut scale: ResMut<SvarogScale>,
+    mut camera: Query<(&mut Camera, &mut OrthographicProjection), With<CameraTag>>,
+) {
+    let Ok(window) = window.get_single() else { return; };
+
+    let target = match (&config.scaling, &tilesets) {
+        (Some(scaling), Some(tilesets)) => scaling_target(scaling, &config.tileset, tilesets),
+        _ => None,
+    };
+
+    let scale_factor = window.scale_factor() as f32;
+    let fitted = SvarogScale::fit(target, Vec2::new(window.physical_width() as f32, window.physical_height() as f32), scale_factor);
+    scale.set_if_neq(fitted);
+
+    let Ok((mut camera, mut projection)) = camera.get_single_mut() else { return; };
+
+    let letterbox = camera.viewport.as_ref().map(|viewport| (viewport.physical_position, viewport.physical_size));
+    if letterbox != fitted.letterbox {
+        camera.viewport = fitted.letterbox.map(|(physical_position, physical_size)| Viewport { physical_position, physical_size, ..Default::default() });
+    }
+
+    let scaling_mode = fitted.scaling_mode(scale_factor);
+    if !matches!((projection.scaling_mode, scaling_mode), (ScalingMode::WindowSize(current), ScalingMode::WindowSize(wanted)) if current == wanted) {
+        projection.scaling_mode = scaling_mode;
+    }
+}
 
 pub struct SvarogWindowPlugin;
 
 impl Plugin for SvarogWindowPlugin {
     fn build(&self, bevy: &mut bevy::prelude::App) {
-        let config_file = File::open("config.ron").expect("Failed to open config file");
-        let Ok(config): Result<Config, _> = ron::de::from_reader(config_file) else {
-            println!("No config found. Quitting.");
-            return;
-        };
+        let (config, error) = SvarogConfig::load(&config_path());
+        report_on(bevy, error);
 
         let mut defaults = DefaultPlugins.build();
         defaults = defaults.set(ImagePlugin::default_nearest());
 
+        let mut resolution = WindowResolution::default();
+        if let Some(scale_factor) = config.scale_factor {
+            resolution = resolution.with_scale_factor_override(scale_factor as f64);
+        }
+
         if let SvarogWindowMode::Windowed(w, h) = config.mode {
+            resolution.set(w as f32, h as f32);
             defaults = defaults.set(WindowPlugin {
                 primary_window: Some(Window {
                     title: config.title.clone(),
                     mode: WindowMode::Windowed,
-                    resolution: WindowResolution::new(w as f32, h as f32),
+                    present_mode: config.present_mode(),
+                    resolution,
                     ..Default::default()
                 }),
                 ..Default::default()
@@ -49,6 +137,8 @@ impl Plugin for SvarogWindowPlugin {
                 primary_window: Some(Window {
                     title: config.title.clone(),
                     mode: WindowMode::BorderlessFullscreen,
+                    present_mode: config.present_mode(),
+                    resolution,
                     ..Default::default()
                 }),
                 ..Default::default()
@@ -56,6 +146,11 @@ impl Plugin for SvarogWindowPlugin {
         }
 
         bevy.add_plugins(defaults);
+        bevy.insert_resource(GlobalVolume::new(config.volume.clamp(0.0, 1.0)));
+        bevy.insert_resource(config);
+        bevy.init_resource::<SvarogScale>();
+        bevy.add_systems(Update, apply_config);
+        bevy.add_systems(PreUpdate, update_scale);
 
         bevy.add_systems(
             Startup,
@@ -66,3 +161,50 @@ impl Plugin for SvarogWindowPlugin {
         );
     }
 }
+
+#[cfg(test)]
+mod windows_testing {
+    use bevy::{math::{UVec2, Vec2}, render::camera::ScalingMode};
+
+    use super::SvarogScale;
+
+    #[test]
+    fn test_scale_fits_target_and_letterboxes() {
+        // 80x50 cells of 16x24 are 1280x1200, which fits only once into a height of 2160
+        let scale = SvarogScale::fit(Some(Vec2::new(1280.0, 1200.0)), Vec2::new(3840.0, 2160.0), 1.0);
+        assert_eq!(scale.scale, 1);
+
+        let scale = SvarogScale::fit(Some(Vec2::new(1280.0, 800.0)), Vec2::new(3840.0, 2160.0), 1.0);
+        assert_eq!(scale.scale, 2);
+        assert_eq!(scale.view, Vec2::new(1280.0, 800.0));
+        assert_eq!(scale.letterbox, Some((UVec2::new(640, 280), UVec2::new(2560, 1600))));
+    }
+
+    #[test]
+    fn test_scale_without_target_uses_window() {
+        let scale = SvarogScale::fit(None, Vec2::new(2048.0, 1536.0), 2.0);
+        assert_eq!(scale, SvarogScale { scale: 1, view: Vec2::new(1024.0, 768.0), letterbox: None });
+    }
+
+    #[test]
+    fn test_window_smaller_than_target_is_clipped() {
+        let scale = SvarogScale::fit(Some(Vec2::new(1280.0, 800.0)), Vec2::new(1024.0, 768.0), 1.0);
+        assert_eq!(scale.scale, 1);
+        assert_eq!(scale.view, Vec2::new(1024.0, 768.0));
+        assert_eq!(scale.letterbox, Some((UVec2::ZERO, UVec2::new(1024, 768))));
+    }
+
+    #[test]
+    fn test_scale_counts_physical_pixels() {
+        // A 2560x1440 window at a scale factor of 1.5 has 3840x2160 physical pixels, so 1280x800 fits twice
+        let scale = SvarogScale::fit(Some(Vec2::new(1280.0, 800.0)), Vec2::new(3840.0, 2160.0), 1.5);
+        assert_eq!(scale.scale, 2);
+        assert_eq!(scale.view, Vec2::new(1280.0, 800.0));
+        assert_eq!(scale.letterbox, Some((UVec2::new(640, 280), UVec2::new(2560, 1600))));
+        assert!(matches!(scale.scaling_mode(1.5), ScalingMode::WindowSize(pixels) if (pixels - 2.0 / 1.5).abs() < f32::EPSILON));
+
+        let scale = SvarogScale::fit(None, Vec2::new(3840.0, 2160.0), 1.5);
+        assert_eq!(scale.view, Vec2::new(2560.0, 1440.0));
+        assert!(matches!(scale.scaling_mode(1.5), ScalingMode::WindowSize(pixels) if pixels == 1.0));
+    }
+}